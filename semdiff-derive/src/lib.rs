@@ -0,0 +1,746 @@
+//! Derive macro for the `Diffable` trait of `semdiff`. This should generally be used through the
+//! `derive` feature of `semdiff`, rather than depended on directly.
+
+use proc_macro::TokenStream;
+use proc_macro2::{TokenStream as TokenStream2, TokenTree};
+use quote::{format_ident, quote};
+use syn::{
+    parse_macro_input, parse_quote, Data, DeriveInput, Expr, Field, Fields, GenericParam, Ident,
+    Index, Lifetime, LitStr, Member, Path, Type, WherePredicate,
+};
+
+/// Derive `Diffable` for a struct or enum, diffing it field-wise using the default algorithm.
+///
+/// For a type `Foo`, this generates a type `FooDiff` with the same shape, holding the diff of each
+/// field. Structs use `FooDiff` directly as their diff, while enums use
+/// `VariantDiff<Foo, FooDiff>`, so that a change of variant can be reported. `FooDiff` implements
+/// `Debug`, `PartialEq` and `semdiff::path::Flatten` whenever the diffs of its fields do.
+///
+/// Fields can be configured with the `#[semdiff(...)]` attribute:
+///
+/// - `skip`: Don't diff this field, and leave it out of the diff type. Changes to it are ignored,
+///   including when `KeyedDiff` decides whether a record changed.
+/// - `algo = "Path"`: Diff this field with the given algorithm, instead of the default.
+/// - `with = path`: Compare this field with a `fn(&T, &T) -> bool`, producing a `ValueDiff`.
+/// - `tolerance = 1e-6`: Compare this float field as equal if within the tolerance, producing a
+///   `ValueDiff`.
+/// - `key`: Use this field as (part of) the identity of the struct, implementing `Keyed` so it can
+///   be used with `KeyedDiff`. Records with the same key are unchanged if every field that isn't
+///   skipped is equal, using `PartialEq` or the field's `with` or `tolerance` comparison.
+#[proc_macro_derive(Diffable, attributes(semdiff))]
+pub fn derive_diffable(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// How a single field is diffed.
+enum Strategy {
+    Algo(Path),
+    With(Path),
+    Tolerance(Expr),
+}
+
+/// Configuration of a field, from its `#[semdiff(...)]` attributes.
+#[derive(Default)]
+struct FieldAttrs {
+    skip: bool,
+    key: bool,
+    strategy: Option<Strategy>,
+}
+
+impl FieldAttrs {
+    fn parse(field: &Field) -> syn::Result<FieldAttrs> {
+        let mut out = FieldAttrs::default();
+        for attr in field.attrs.iter().filter(|a| a.path().is_ident("semdiff")) {
+            attr.parse_nested_meta(|meta| {
+                let strategy = if meta.path.is_ident("skip") {
+                    out.skip = true;
+                    return Ok(());
+                } else if meta.path.is_ident("key") {
+                    out.key = true;
+                    return Ok(());
+                } else if meta.path.is_ident("algo") {
+                    let path: LitStr = meta.value()?.parse()?;
+                    Strategy::Algo(path.parse()?)
+                } else if meta.path.is_ident("with") {
+                    Strategy::With(meta.value()?.parse()?)
+                } else if meta.path.is_ident("tolerance") {
+                    Strategy::Tolerance(meta.value()?.parse()?)
+                } else {
+                    return Err(meta.error("unrecognized semdiff attribute"));
+                };
+                if out.strategy.is_some() {
+                    return Err(meta.error("only one of `algo`, `with` or `tolerance` may be used"));
+                }
+                out.strategy = Some(strategy);
+                Ok(())
+            })?;
+        }
+        Ok(out)
+    }
+}
+
+/// A field of the input which will be diffed.
+struct DiffField<'a> {
+    field: &'a Field,
+    member: Member,
+    strategy: Option<Strategy>,
+}
+
+impl DiffField<'_> {
+    fn ty(&self) -> &Type {
+        &self.field.ty
+    }
+
+    fn algo(&self) -> TokenStream2 {
+        match &self.strategy {
+            Some(Strategy::Algo(path)) => quote!(#path),
+            _ => quote!(::semdiff::algo::Default),
+        }
+    }
+
+    /// The type of this field's diff, in the generated diff type.
+    fn diff_ty(&self, lt: &Lifetime) -> TokenStream2 {
+        let ty = self.ty();
+        match &self.strategy {
+            Some(Strategy::With(_) | Strategy::Tolerance(_)) => {
+                quote!(::semdiff::builtin::ValueDiff<#lt, #ty>)
+            }
+            _ => {
+                let algo = self.algo();
+                quote!(<#ty as ::semdiff::Diffable>::Diff<#lt, #algo>)
+            }
+        }
+    }
+
+    /// An expression comparing this field between `l` and `r`, if it is compared rather than diffed.
+    fn eq_expr(&self, l: &TokenStream2, r: &TokenStream2) -> Option<TokenStream2> {
+        match &self.strategy {
+            Some(Strategy::With(path)) => Some(quote!(#path(#l, #r))),
+            Some(Strategy::Tolerance(tol)) => Some(quote!(*#l == *#r || (*#l - *#r).abs() <= #tol)),
+            _ => None,
+        }
+    }
+
+    /// An expression diffing this field between the bindings `l` and `r`.
+    fn diff_expr(&self, l: &TokenStream2, r: &TokenStream2) -> TokenStream2 {
+        let ty = self.ty();
+        let Some(eq) = self.eq_expr(l, r) else {
+            let algo = self.algo();
+            return quote!(<#ty as ::semdiff::Diffable>::diff::<#algo>(#l, #r));
+        };
+        quote! {
+            if #eq {
+                ::semdiff::builtin::ValueDiff::Equal(#l)
+            } else {
+                ::semdiff::builtin::ValueDiff::Changed(#l, #r)
+            }
+        }
+    }
+
+    /// Bounds required for this field to be diffed.
+    fn bounds(&self) -> Vec<WherePredicate> {
+        let ty = self.ty();
+        match &self.strategy {
+            Some(Strategy::With(_) | Strategy::Tolerance(_)) => Vec::new(),
+            _ => {
+                let algo = self.algo();
+                vec![
+                    parse_quote!(#ty: ::semdiff::Diffable),
+                    parse_quote!(
+                        #algo: ::semdiff::algo::DiffAlgo<<#ty as ::semdiff::Diffable>::Item>
+                    ),
+                ]
+            }
+        }
+    }
+}
+
+fn member(idx: usize, field: &Field) -> Member {
+    match &field.ident {
+        Some(ident) => Member::Named(ident.clone()),
+        None => Member::Unnamed(Index::from(idx)),
+    }
+}
+
+/// Get the fields to diff, and the fields marked as keys.
+fn diff_fields(fields: &Fields) -> syn::Result<(Vec<DiffField<'_>>, Vec<&Field>)> {
+    let mut diffed = Vec::new();
+    let mut keys = Vec::new();
+    for (idx, field) in fields.iter().enumerate() {
+        let attrs = FieldAttrs::parse(field)?;
+        if attrs.key {
+            keys.push(field);
+        }
+        if attrs.skip {
+            if attrs.strategy.is_some() {
+                return Err(syn::Error::new_spanned(
+                    field,
+                    "skipped fields cannot have a diff strategy",
+                ));
+            }
+            continue;
+        }
+        diffed.push(DiffField {
+            field,
+            member: member(idx, field),
+            strategy: attrs.strategy,
+        });
+    }
+    Ok((diffed, keys))
+}
+
+/// Implement `Keyed` for the input, using the given fields as its identity. Values are unchanged if
+/// each diffed field is equal, or compares equal with its `with` or `tolerance` expression, so
+/// skipped fields don't count.
+fn keyed(
+    input: &DeriveInput,
+    fields: &Fields,
+    keys: &[&Field],
+    diffed: &[DiffField<'_>],
+) -> TokenStream2 {
+    let name = &input.ident;
+    let lt: Lifetime = parse_quote!('key);
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let mut where_clause = where_clause.cloned().unwrap_or_else(|| parse_quote!(where));
+
+    let mut tys = Vec::new();
+    let mut exprs = Vec::new();
+    for (idx, field) in fields.iter().enumerate() {
+        if !keys.iter().any(|k| std::ptr::eq(*k, field)) {
+            continue;
+        }
+        let ty = &field.ty;
+        let member = member(idx, field);
+        where_clause
+            .predicates
+            .push(parse_quote!(#ty: ::core::cmp::Eq + ::core::hash::Hash));
+        tys.push(quote!(&#lt #ty));
+        exprs.push(quote!(&self.#member));
+    }
+    let (key_ty, key_expr) = if tys.len() == 1 {
+        (tys.remove(0), exprs.remove(0))
+    } else {
+        (quote!((#(#tys,)*)), quote!((#(#exprs,)*)))
+    };
+
+    // Whether each diffed field is unchanged. Fields of a generic type need a bound to be compared.
+    let type_params = input
+        .generics
+        .type_params()
+        .map(|p| &p.ident)
+        .collect::<Vec<_>>();
+    let unchanged = diffed.iter().map(|f| {
+        let member = &f.member;
+        let (l, r) = (quote!(&self.#member), quote!(&other.#member));
+        if let Some(eq) = f.eq_expr(&l, &r) {
+            return eq;
+        }
+        let ty = f.ty();
+        if type_params.iter().any(|p| mentions(quote!(#ty), p)) {
+            where_clause
+                .predicates
+                .push(parse_quote!(#ty: ::core::cmp::PartialEq));
+        }
+        quote!(#l == #r)
+    });
+    let unchanged = unchanged.collect::<Vec<_>>();
+
+    quote! {
+        impl #impl_generics ::semdiff::builtin::Keyed for #name #ty_generics #where_clause {
+            type Key<#lt> = #key_ty
+            where
+                Self: #lt;
+
+            fn key(&self) -> Self::Key<'_> {
+                #key_expr
+            }
+
+            fn is_unchanged(&self, other: &Self) -> bool {
+                true #(&& #unchanged)*
+            }
+        }
+    }
+}
+
+/// Check whether a token stream mentions an identifier anywhere, including in nested groups.
+fn mentions(tokens: TokenStream2, ident: &Ident) -> bool {
+    tokens.into_iter().any(|tt| match tt {
+        TokenTree::Ident(i) => i == *ident,
+        TokenTree::Group(g) => mentions(g.stream(), ident),
+        _ => false,
+    })
+}
+
+/// Generate the field list of a diff type, with the same shape as the input fields.
+fn def_fields(
+    fields: &Fields,
+    diffed: &[DiffField<'_>],
+    lt: &Lifetime,
+    marker: Option<&TokenStream2>,
+) -> TokenStream2 {
+    let defs = diffed.iter().map(|f| {
+        let vis = &f.field.vis;
+        let ty = f.diff_ty(lt);
+        match &f.field.ident {
+            Some(ident) => quote!(#vis #ident: #ty),
+            None => quote!(#vis #ty),
+        }
+    });
+    let marker_def = marker.map(|m| match fields {
+        Fields::Named(_) => quote!(#[doc(hidden)] pub __marker: #m),
+        _ => quote!(#[doc(hidden)] pub #m),
+    });
+    let defs = defs.chain(marker_def);
+    match fields {
+        Fields::Named(_) => quote!({ #(#defs,)* }),
+        Fields::Unnamed(_) => quote!(( #(#defs,)* )),
+        Fields::Unit if marker.is_some() => quote!(( #(#defs,)* )),
+        Fields::Unit => quote!(),
+    }
+}
+
+/// Generate a constructor for a diff type, with each field diffed between `l_*` and `r_*` bindings.
+fn construct(
+    path: TokenStream2,
+    fields: &Fields,
+    diffed: &[DiffField<'_>],
+    marker: bool,
+) -> TokenStream2 {
+    let inits = diffed.iter().map(|f| {
+        let (l, r) = bindings(&f.member);
+        let expr = f.diff_expr(&quote!(#l), &quote!(#r));
+        match &f.member {
+            Member::Named(ident) => quote!(#ident: #expr),
+            Member::Unnamed(_) => expr,
+        }
+    });
+    let marker_init = marker.then(|| match fields {
+        Fields::Named(_) => quote!(__marker: ::core::marker::PhantomData),
+        _ => quote!(::core::marker::PhantomData),
+    });
+    let inits = inits.chain(marker_init);
+    match fields {
+        Fields::Named(_) => quote!(#path { #(#inits,)* }),
+        Fields::Unnamed(_) => quote!(#path( #(#inits,)* )),
+        Fields::Unit if marker => quote!(#path( #(#inits,)* )),
+        Fields::Unit => quote!(#path),
+    }
+}
+
+/// Generate a pattern binding every field of the input with the given prefix.
+fn pattern(path: TokenStream2, fields: &Fields, left: bool) -> TokenStream2 {
+    let binds = fields.iter().enumerate().map(|(idx, field)| {
+        let member = member(idx, field);
+        let (l, r) = bindings(&member);
+        let bind = if left { l } else { r };
+        quote!(#member: #bind)
+    });
+    match fields {
+        Fields::Unit => quote!(#path),
+        _ => quote!(#path { #(#binds,)* }),
+    }
+}
+
+fn bindings(member: &Member) -> (Ident, Ident) {
+    match member {
+        Member::Named(ident) => (format_ident!("l_{}", ident), format_ident!("r_{}", ident)),
+        Member::Unnamed(idx) => (
+            format_ident!("l_{}", idx.index),
+            format_ident!("r_{}", idx.index),
+        ),
+    }
+}
+
+/// A struct or enum variant of the generated diff type.
+struct Shape<'a> {
+    path: TokenStream2,
+    name: String,
+    fields: &'a Fields,
+    diffed: &'a [DiffField<'a>],
+}
+
+impl Shape<'_> {
+    /// A pattern for this shape, binding the diff of each field by reference.
+    fn pattern(&self, left: bool) -> TokenStream2 {
+        let path = &self.path;
+        let binds = self.diffed.iter().map(|f| {
+            let member = &f.member;
+            let (l, r) = bindings(member);
+            let bind = if left { l } else { r };
+            quote!(#member: #bind)
+        });
+        quote!(#path { #(#binds,)* .. })
+    }
+
+    fn debug(&self) -> TokenStream2 {
+        let name = &self.name;
+        let fields = self.diffed.iter().map(|f| {
+            let (l, _) = bindings(&f.member);
+            match &f.member {
+                Member::Named(ident) => {
+                    let ident = ident.to_string();
+                    quote!(.field(#ident, #l))
+                }
+                Member::Unnamed(_) => quote!(.field(#l)),
+            }
+        });
+        let pat = self.pattern(true);
+        match self.fields {
+            Fields::Named(_) => quote!(#pat => f.debug_struct(#name)#(#fields)*.finish()),
+            Fields::Unnamed(_) => quote!(#pat => f.debug_tuple(#name)#(#fields)*.finish()),
+            Fields::Unit => quote!(#pat => f.write_str(#name)),
+        }
+    }
+
+    /// Flatten the diff of each field, located by its name or index. Variants add no segment.
+    fn flatten(&self) -> TokenStream2 {
+        let fields = self.diffed.iter().map(|f| {
+            let (l, _) = bindings(&f.member);
+            let seg = match &f.member {
+                Member::Named(ident) => {
+                    let ident = ident.to_string();
+                    quote!(::semdiff::path::Segment::Field(#ident))
+                }
+                Member::Unnamed(idx) => {
+                    let idx = idx.index as usize;
+                    quote!(::semdiff::path::Segment::Index(#idx))
+                }
+            };
+            quote!(::semdiff::path::Flatten::flatten_into(#l, &path.with(#seg), out);)
+        });
+        let pat = self.pattern(true);
+        quote!(#pat => { #(#fields)* })
+    }
+
+    fn eq(&self) -> TokenStream2 {
+        let cmps = self.diffed.iter().map(|f| {
+            let (l, r) = bindings(&f.member);
+            quote!(&& #l == #r)
+        });
+        let pat_l = self.pattern(true);
+        let pat_r = self.pattern(false);
+        quote!((#pat_l, #pat_r) => true #(#cmps)*)
+    }
+}
+
+/// Implement `Debug`, `PartialEq` and `Flatten` for the generated diff type. These can't be
+/// derived, as the standard derives don't bound on the diff types of fields.
+fn diff_traits(
+    diff_ty: &TokenStream2,
+    generics: &syn::Generics,
+    shapes: &[Shape<'_>],
+    marker: bool,
+    lt: &Lifetime,
+) -> TokenStream2 {
+    let (impl_generics, _, where_clause) = generics.split_for_impl();
+    let mut debug_where = where_clause.cloned().unwrap_or_else(|| parse_quote!(where));
+    let mut eq_where = debug_where.clone();
+    let mut flatten_where = debug_where.clone();
+    for f in shapes.iter().flat_map(|s| s.diffed) {
+        let ty = f.diff_ty(lt);
+        debug_where
+            .predicates
+            .push(parse_quote!(#ty: ::core::fmt::Debug));
+        eq_where
+            .predicates
+            .push(parse_quote!(#ty: ::core::cmp::PartialEq));
+        flatten_where
+            .predicates
+            .push(parse_quote!(#ty: ::semdiff::path::Flatten));
+    }
+
+    let debug_arms = shapes.iter().map(Shape::debug);
+    let eq_arms = shapes.iter().map(Shape::eq);
+    let flatten_arms = shapes.iter().map(Shape::flatten);
+    let marker_arm = marker.then(|| quote!(Self::__Marker(never, _) => match *never {},));
+
+    let (debug_body, eq_body, flatten_body) = if shapes.is_empty() && !marker {
+        (
+            quote!(match *self {}),
+            quote!(match *self {}),
+            quote!(match *self {}),
+        )
+    } else {
+        (
+            quote! {
+                match self {
+                    #(#debug_arms,)*
+                    #marker_arm
+                }
+            },
+            quote! {
+                match (self, other) {
+                    #(#eq_arms,)*
+                    _ => false,
+                }
+            },
+            quote! {
+                match self {
+                    #(#flatten_arms,)*
+                    #marker_arm
+                }
+            },
+        )
+    };
+
+    quote! {
+        impl #impl_generics ::core::fmt::Debug for #diff_ty #debug_where {
+            fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+                #debug_body
+            }
+        }
+
+        impl #impl_generics ::core::cmp::PartialEq for #diff_ty #eq_where {
+            #[allow(unreachable_patterns, unused_variables)]
+            fn eq(&self, other: &Self) -> bool {
+                #eq_body
+            }
+        }
+
+        impl #impl_generics ::semdiff::path::Flatten for #diff_ty #flatten_where {
+            #[allow(unused_variables)]
+            fn flatten_into<'__flat>(
+                &'__flat self,
+                path: &::semdiff::path::DiffPath,
+                out: &mut ::std::vec::Vec<::semdiff::path::FlatChange<'__flat>>,
+            ) {
+                #flatten_body
+            }
+        }
+    }
+}
+
+fn expand(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let name = &input.ident;
+    let vis = &input.vis;
+    let diff_name = format_ident!("{}Diff", name);
+    let lt: Lifetime = parse_quote!('diff);
+
+    let all_fields: Vec<&Fields> = match &input.data {
+        Data::Struct(data) => vec![&data.fields],
+        Data::Enum(data) => data.variants.iter().map(|v| &v.fields).collect(),
+        Data::Union(data) => {
+            return Err(syn::Error::new(
+                data.union_token.span,
+                "Diffable cannot be derived for unions",
+            ))
+        }
+    };
+    let mut diffed = Vec::new();
+    let mut keyed_impl = None;
+    for fields in all_fields {
+        let (d, keys) = diff_fields(fields)?;
+        if !keys.is_empty() {
+            if let Data::Enum(_) = input.data {
+                return Err(syn::Error::new_spanned(
+                    keys[0],
+                    "key fields are only supported on structs",
+                ));
+            }
+            keyed_impl = Some(keyed(&input, fields, &keys, &d));
+        }
+        diffed.push(d);
+    }
+
+    // Every generic parameter of the diff type must be used by some field, or be covered by a
+    // marker. If there is nothing to diff and nothing generic, the diff type needs no parameters.
+    let any_diffed = diffed.iter().any(|d| !d.is_empty());
+    let has_generics = any_diffed || !input.generics.params.is_empty();
+    let field_tokens: TokenStream2 = diffed
+        .iter()
+        .flatten()
+        .map(|f| {
+            let ty = f.ty();
+            quote!(#ty)
+        })
+        .collect();
+    let unused = input.generics.params.iter().any(|param| {
+        let ident = match param {
+            GenericParam::Type(ty) => &ty.ident,
+            GenericParam::Lifetime(lt) => &lt.lifetime.ident,
+            GenericParam::Const(c) => &c.ident,
+        };
+        !mentions(field_tokens.clone(), ident)
+    });
+    let marker = (has_generics && (unused || !any_diffed)).then(|| {
+        let params = input
+            .generics
+            .params
+            .iter()
+            .filter_map(|param| match param {
+                GenericParam::Type(ty) => {
+                    let ident = &ty.ident;
+                    Some(quote!(#ident))
+                }
+                GenericParam::Lifetime(l) => {
+                    let l = &l.lifetime;
+                    Some(quote!(&#l ()))
+                }
+                GenericParam::Const(_) => None,
+            });
+        quote!(::core::marker::PhantomData<fn() -> (&#lt (), #(#params,)*)>)
+    });
+
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let mut diff_generics = input.generics.clone();
+    if has_generics {
+        diff_generics.params.insert(0, parse_quote!(#lt));
+    }
+    let bounds: Vec<WherePredicate> = diffed
+        .iter()
+        .flatten()
+        .flat_map(DiffField::bounds)
+        .collect();
+    diff_generics
+        .make_where_clause()
+        .predicates
+        .extend(bounds.iter().cloned());
+    diff_generics
+        .make_where_clause()
+        .predicates
+        .extend(diffed.iter().flatten().map(|f| -> WherePredicate {
+            let ty = f.ty();
+            parse_quote!(#ty: #lt)
+        }));
+    let (diff_impl_generics, diff_ty_generics, diff_where_clause) = diff_generics.split_for_impl();
+
+    let mut algo_generics = input.generics.clone();
+    algo_generics.make_where_clause().predicates.extend(bounds);
+    let (algo_impl_generics, _, algo_where_clause) = algo_generics.split_for_impl();
+
+    let (diff_def, diff_ty, shapes, body) = match &input.data {
+        Data::Struct(data) => {
+            let fields = def_fields(&data.fields, &diffed[0], &lt, marker.as_ref());
+            let def = match data.fields {
+                Fields::Named(_) => quote! {
+                    #vis struct #diff_name #diff_impl_generics #diff_where_clause #fields
+                },
+                _ => quote! {
+                    #vis struct #diff_name #diff_impl_generics #fields #diff_where_clause;
+                },
+            };
+            let pat_l = pattern(quote!(#name), &data.fields, true);
+            let pat_r = pattern(quote!(#name), &data.fields, false);
+            let cons = construct(
+                quote!(#diff_name),
+                &data.fields,
+                &diffed[0],
+                marker.is_some(),
+            );
+            let body = quote! {
+                let #pat_l = l;
+                let #pat_r = r;
+                #cons
+            };
+            let shape = Shape {
+                path: quote!(#diff_name),
+                name: diff_name.to_string(),
+                fields: &data.fields,
+                diffed: &diffed[0],
+            };
+            (def, quote!(#diff_name #diff_ty_generics), vec![shape], body)
+        }
+        Data::Enum(data) => {
+            let variants = data.variants.iter().zip(&diffed).map(|(v, d)| {
+                let ident = &v.ident;
+                let fields = def_fields(&v.fields, d, &lt, None);
+                quote!(#ident #fields)
+            });
+            let marker_variant = marker
+                .as_ref()
+                .map(|m| quote!(#[doc(hidden)] __Marker(::core::convert::Infallible, #m)));
+            let variants = variants.chain(marker_variant);
+            let def = quote! {
+                #vis enum #diff_name #diff_impl_generics #diff_where_clause {
+                    #(#variants,)*
+                }
+            };
+
+            let arms = data.variants.iter().zip(&diffed).map(|(v, d)| {
+                let ident = &v.ident;
+                let pat_l = pattern(quote!(#name::#ident), &v.fields, true);
+                let pat_r = pattern(quote!(#name::#ident), &v.fields, false);
+                let cons = construct(quote!(#diff_name::#ident), &v.fields, d, false);
+                quote!((#pat_l, #pat_r) => ::semdiff::builtin::VariantDiff::Same(#cons))
+            });
+            let body = if data.variants.is_empty() {
+                quote!(match *l {})
+            } else {
+                quote! {
+                    match (l, r) {
+                        #(#arms,)*
+                        _ => ::semdiff::builtin::VariantDiff::Changed(l, r),
+                    }
+                }
+            };
+            let ty = quote! {
+                ::semdiff::builtin::VariantDiff<#lt, #name #ty_generics, #diff_name #diff_ty_generics>
+            };
+            let shapes = data
+                .variants
+                .iter()
+                .zip(&diffed)
+                .map(|(v, d)| {
+                    let ident = &v.ident;
+                    Shape {
+                        path: quote!(#diff_name::#ident),
+                        name: ident.to_string(),
+                        fields: &v.fields,
+                        diffed: d,
+                    }
+                })
+                .collect();
+            (def, ty, shapes, body)
+        }
+        Data::Union(_) => unreachable!(),
+    };
+
+    let is_enum = matches!(input.data, Data::Enum(_));
+    let traits = diff_traits(
+        &quote!(#diff_name #diff_ty_generics),
+        &diff_generics,
+        &shapes,
+        is_enum && marker.is_some(),
+        &lt,
+    );
+
+    Ok(quote! {
+        #diff_def
+
+        #traits
+
+        #keyed_impl
+
+        impl #algo_impl_generics ::semdiff::algo::DiffAlgo<#name #ty_generics>
+            for ::semdiff::algo::Default
+        #algo_where_clause
+        {
+            type Diff<#lt> = #diff_ty
+            where
+                #name #ty_generics: #lt;
+
+            #[allow(unused_variables, unreachable_patterns)]
+            fn diff<#lt>(l: &#lt #name #ty_generics, r: &#lt #name #ty_generics) -> Self::Diff<#lt> {
+                #body
+            }
+        }
+
+        impl #impl_generics ::semdiff::Diffable for #name #ty_generics #where_clause {
+            type Diff<#lt, A: ::semdiff::algo::DiffAlgo<Self::Item>> = A::Diff<#lt>
+            where
+                Self: #lt;
+            type Item = Self;
+
+            fn diff<#lt, A: ::semdiff::algo::DiffAlgo<Self::Item>>(
+                &#lt self,
+                other: &#lt Self,
+            ) -> Self::Diff<#lt, A> {
+                A::diff(self, other)
+            }
+        }
+    })
+}
@@ -0,0 +1,316 @@
+use semdiff::algo::DiffAlgo;
+use semdiff::builtin::{KeyedDiff, KeyedEntry, MapEntry, ValueDiff, VariantDiff};
+use semdiff::path::{DiffPath, Flatten, Segment};
+use semdiff::{diff, DiffRes, Diffable};
+use std::marker::PhantomData;
+
+#[derive(Debug, PartialEq, Diffable)]
+struct Named {
+    name: String,
+    count: u32,
+}
+
+#[derive(Debug, PartialEq, Diffable)]
+struct Tuple(u8, Vec<u8>);
+
+#[derive(Debug, PartialEq, Diffable)]
+struct Unit;
+
+#[derive(Debug, PartialEq, Diffable)]
+struct Generic<T> {
+    inner: Option<T>,
+}
+
+#[derive(Debug, PartialEq, Diffable)]
+enum Shape {
+    Point,
+    Circle(f64),
+    Rect { w: f64, h: f64 },
+}
+
+#[test]
+fn test_named() {
+    let a = Named {
+        name: String::from("a"),
+        count: 1,
+    };
+    let b = Named {
+        name: String::from("b"),
+        count: 1,
+    };
+
+    assert_eq!(
+        diff(&a, &b),
+        NamedDiff {
+            name: vec![DiffRes::Left("a"), DiffRes::Right("b")],
+            count: ValueDiff::Equal(&1),
+        },
+    );
+}
+
+#[test]
+fn test_tuple() {
+    let a = Tuple(1, vec![1, 2]);
+    let b = Tuple(2, vec![1, 2]);
+
+    assert_eq!(
+        diff(&a, &b),
+        TupleDiff(
+            ValueDiff::Changed(&1, &2),
+            vec![DiffRes::Both(&[1u8, 2] as &[_], &[1, 2])],
+        ),
+    );
+    assert_eq!(diff(&Unit, &Unit), UnitDiff);
+}
+
+#[test]
+fn test_generic() {
+    let a = Generic { inner: Some(1) };
+    let b = Generic { inner: Some(2) };
+
+    assert_eq!(
+        diff(&a, &b),
+        GenericDiff {
+            inner: VariantDiff::Same(Some(ValueDiff::Changed(&1, &2))),
+        },
+    );
+}
+
+#[test]
+fn test_enum() {
+    let a = Shape::Rect { w: 1.0, h: 2.0 };
+    let b = Shape::Rect { w: 1.0, h: 3.0 };
+
+    assert_eq!(
+        diff(&a, &b),
+        VariantDiff::Same(ShapeDiff::Rect {
+            w: ValueDiff::Equal(&1.0),
+            h: ValueDiff::Changed(&2.0, &3.0),
+        }),
+    );
+    assert_eq!(
+        diff(&Shape::Circle(1.0), &Shape::Circle(1.0)),
+        VariantDiff::Same(ShapeDiff::Circle(ValueDiff::Equal(&1.0))),
+    );
+    assert_eq!(
+        diff(&Shape::Point, &Shape::Point),
+        VariantDiff::Same(ShapeDiff::Point)
+    );
+    assert_eq!(
+        diff(&a, &Shape::Point),
+        VariantDiff::Changed(&a, &Shape::Point)
+    );
+}
+
+#[derive(Debug, PartialEq, Diffable)]
+struct Borrowed<'a> {
+    text: &'a str,
+}
+
+#[derive(Debug, PartialEq, Diffable)]
+enum Never {}
+
+#[test]
+fn test_borrowed() {
+    let a = Borrowed { text: "a\nb" };
+    let b = Borrowed { text: "a" };
+
+    assert_eq!(
+        diff(&a, &b),
+        BorrowedDiff {
+            text: vec![DiffRes::Both("a", "a"), DiffRes::Left("b")],
+        },
+    );
+}
+
+fn same_len(l: &str, r: &str) -> bool {
+    l.len() == r.len()
+}
+
+#[derive(Debug, PartialEq, Diffable)]
+struct Record {
+    #[semdiff(key)]
+    id: u32,
+    #[semdiff(skip)]
+    updated: u64,
+    #[semdiff(algo = "semdiff::builtin::MultisetDiff")]
+    tags: Vec<&'static str>,
+    #[semdiff(with = same_len)]
+    code: String,
+    #[semdiff(tolerance = 1e-6)]
+    score: f64,
+}
+
+#[derive(Debug, PartialEq, Diffable)]
+struct Tagged<T> {
+    value: u8,
+    #[semdiff(skip)]
+    marker: PhantomData<T>,
+}
+
+#[test]
+fn test_attrs() {
+    let a = Record {
+        id: 1,
+        updated: 100,
+        tags: vec!["a", "b"],
+        code: String::from("abc"),
+        score: 0.5,
+    };
+    let b = Record {
+        id: 1,
+        updated: 200,
+        tags: vec!["b", "a"],
+        code: String::from("xyz"),
+        score: 0.5 + 1e-9,
+    };
+
+    assert_eq!(
+        diff(&a, &b),
+        RecordDiff {
+            id: ValueDiff::Equal(&1),
+            tags: vec![
+                DiffRes::Both((&"a", 1), (&"a", 1)),
+                DiffRes::Both((&"b", 1), (&"b", 1)),
+            ],
+            code: ValueDiff::Equal(&a.code),
+            score: ValueDiff::Equal(&a.score),
+        },
+    );
+
+    let c = Record {
+        id: 2,
+        updated: 0,
+        tags: vec![],
+        code: String::from("abcd"),
+        score: 1.0,
+    };
+    assert_eq!(diff(&a, &c).code, ValueDiff::Changed(&a.code, &c.code));
+    assert_eq!(diff(&a, &c).score, ValueDiff::Changed(&a.score, &c.score));
+
+    let a = [a, c];
+    let b = [b];
+    let d = a[..].diff::<KeyedDiff>(&b[..]);
+    assert_eq!(d.len(), 2);
+    assert_eq!(
+        d[0],
+        KeyedEntry {
+            left: Some(0),
+            right: Some(0),
+            moved: false,
+            entry: MapEntry::Unchanged(&a[0]),
+        },
+    );
+    assert_eq!(
+        d[1],
+        KeyedEntry {
+            left: Some(1),
+            right: None,
+            moved: false,
+            entry: MapEntry::Removed(&a[1]),
+        },
+    );
+}
+
+/// Diffs strings by their change in length, which has no `Flatten` impl.
+struct LenDiff;
+
+impl DiffAlgo<str> for LenDiff {
+    type Diff<'a> = isize;
+
+    fn diff<'a>(l: &'a str, r: &'a str) -> isize {
+        r.len() as isize - l.len() as isize
+    }
+}
+
+#[derive(Debug, PartialEq, Diffable)]
+struct Package {
+    #[semdiff(key)]
+    name: &'static str,
+    #[semdiff(algo = "LenDiff")]
+    version: String,
+}
+
+#[test]
+fn test_keyed_algo() {
+    let package = |name, version: &str| Package {
+        name,
+        version: version.to_owned(),
+    };
+    let a = [package("serde", "1.0"), package("syn", "2.0")];
+    let b = [package("serde", "1.0"), package("syn", "2.0.1")];
+    let d = a[..].diff::<KeyedDiff>(&b[..]);
+
+    assert_eq!(d[0].entry, MapEntry::Unchanged(&a[0]));
+    assert_eq!(
+        d[1].entry,
+        MapEntry::Changed(PackageDiff {
+            name: vec![DiffRes::Both("syn", "syn")],
+            version: 2,
+        }),
+    );
+}
+
+#[test]
+fn test_skip_generic() {
+    let a = Tagged::<String> {
+        value: 1,
+        marker: PhantomData,
+    };
+    let b = Tagged {
+        value: 2,
+        marker: PhantomData,
+    };
+
+    assert_eq!(diff(&a, &b).value, ValueDiff::Changed(&1, &2));
+}
+
+#[derive(Debug, PartialEq, Diffable)]
+struct Config {
+    name: String,
+    shapes: Vec<Shape>,
+    main: Shape,
+    pair: (u8, Tuple),
+}
+
+#[test]
+fn test_flatten() {
+    let a = Config {
+        name: String::from("a"),
+        shapes: vec![Shape::Point],
+        main: Shape::Rect { w: 1.0, h: 2.0 },
+        pair: (1, Tuple(1, vec![1])),
+    };
+    let b = Config {
+        name: String::from("a"),
+        shapes: vec![Shape::Point, Shape::Circle(1.0)],
+        main: Shape::Rect { w: 1.0, h: 3.0 },
+        pair: (1, Tuple(2, vec![1])),
+    };
+    let d = diff(&a, &b);
+
+    let changes = d
+        .flatten()
+        .into_iter()
+        .map(|(path, res)| {
+            let res = match res {
+                DiffRes::Left(l) => format!("-{:?}", l),
+                DiffRes::Both(l, r) => format!("{:?} -> {:?}", l, r),
+                DiffRes::Right(r) => format!("+{:?}", r),
+            };
+            format!("{} {}", path, res)
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        changes,
+        vec![
+            ".shapes[1] +Circle(1.0)",
+            ".main.h 2.0 -> 3.0",
+            ".pair[1][0] 1 -> 2",
+        ],
+    );
+    assert_eq!(
+        d.flatten()[1].0,
+        DiffPath(vec![Segment::Field("main"), Segment::Field("h")])
+    );
+}
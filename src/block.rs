@@ -0,0 +1,310 @@
+use crate::algo::{DiffAlgo, DiffPatch};
+use crate::DiffRes;
+use std::collections::HashMap;
+use std::hash::{DefaultHasher, Hasher};
+
+/// Generate a diff of two byte buffers using rsync-style rolling checksums. The left side is split
+/// into blocks of `N` bytes, and the right side is scanned for any window matching one of those
+/// blocks. This is linear in the size of the inputs, so remains usable for very large binaries where
+/// [`LcsDiff`](crate::builtin::LcsDiff) is not.
+///
+/// Windows with a matching weak checksum are confirmed with a strong hash of the block, as rsync
+/// does, so a bucket of colliding blocks is never compared byte by byte. Repeated blocks are only
+/// kept once, and at most 16 blocks are checked for each window, so many blocks
+/// sharing a checksum can't make the scan quadratic. A block size of zero fails to compile.
+///
+/// [`DiffRes::Both`] is a range copied from the left side (possibly from a different offset, if it
+/// was moved), [`DiffRes::Right`] is new data, and [`DiffRes::Left`] is a range of the left side not
+/// used by any copy. Copies and new data are ordered by position in the right side, so applying them
+/// in order rebuilds it. Unused left ranges are ordered by their left offset, each placed before the
+/// first copy taken from later in the left side.
+pub struct BlockDiff<const N: usize = 2048>;
+
+/// A range of bytes taken from one of the inputs of a [`BlockDiff`].
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub struct Chunk<'a> {
+    /// Offset of this range in the input it was taken from.
+    pub offset: usize,
+    /// The bytes in this range.
+    pub data: &'a [u8],
+}
+
+impl<'a> Chunk<'a> {
+    fn new(buf: &'a [u8], offset: usize, len: usize) -> Chunk<'a> {
+        Chunk {
+            offset,
+            data: &buf[offset..offset + len],
+        }
+    }
+
+    /// The offset one past the end of this range.
+    pub fn end(&self) -> usize {
+        self.offset + self.data.len()
+    }
+}
+
+/// The weak rolling checksum used by rsync. Can be updated in constant time as the window moves.
+#[derive(Copy, Clone)]
+struct Rolling {
+    a: u32,
+    b: u32,
+    len: u32,
+}
+
+impl Rolling {
+    fn new(window: &[u8]) -> Rolling {
+        let len = window.len() as u32;
+        let mut a = 0u32;
+        let mut b = 0u32;
+        for (idx, &byte) in window.iter().enumerate() {
+            a = a.wrapping_add(byte as u32);
+            b = b.wrapping_add((len - idx as u32).wrapping_mul(byte as u32));
+        }
+        Rolling { a, b, len }
+    }
+
+    fn roll(&mut self, out: u8, inp: u8) {
+        self.a = self.a.wrapping_sub(out as u32).wrapping_add(inp as u32);
+        self.b = self
+            .b
+            .wrapping_sub(self.len.wrapping_mul(out as u32))
+            .wrapping_add(self.a);
+    }
+
+    fn digest(&self) -> u32 {
+        (self.b << 16) | (self.a & 0xFFFF)
+    }
+}
+
+/// The most blocks with the same weak checksum checked against each window.
+const MAX_CANDIDATES: usize = 16;
+
+/// The strong hash of a block, used to confirm a match of the weak checksum.
+fn strong_hash(block: &[u8]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    hasher.write(block);
+    hasher.finish()
+}
+
+impl<const N: usize> BlockDiff<N> {
+    const NON_ZERO: () = assert!(N > 0, "BlockDiff block size must be non-zero");
+}
+
+impl<const N: usize> DiffAlgo<[u8]> for BlockDiff<N> {
+    type Diff<'a> = Vec<DiffRes<Chunk<'a>>>;
+
+    fn diff<'a>(l: &'a [u8], r: &'a [u8]) -> Self::Diff<'a> {
+        let () = Self::NON_ZERO;
+
+        // (strong hash, offset) of each block, by weak checksum
+        let mut blocks: HashMap<u32, Vec<(u64, usize)>> = HashMap::new();
+        for start in (0..l.len()).step_by(N) {
+            if start + N > l.len() {
+                break;
+            }
+            let block = &l[start..start + N];
+            let strong = strong_hash(block);
+            let cands = blocks.entry(Rolling::new(block).digest()).or_default();
+            let repeated = cands
+                .iter()
+                .any(|&(hash, prev)| hash == strong && &l[prev..prev + N] == block);
+            if !repeated && cands.len() < MAX_CANDIDATES {
+                cands.push((strong, start));
+            }
+        }
+
+        // (left offset, right offset, len) of each copied range, in right order
+        let mut copies: Vec<(usize, usize, usize)> = Vec::new();
+        let mut literal = 0;
+        let mut pos = 0;
+        let mut sum = (r.len() >= N).then(|| Rolling::new(&r[..N]));
+
+        while let Some(cur) = sum {
+            let window = &r[pos..pos + N];
+            let found = blocks.get(&cur.digest()).and_then(|cands| {
+                let strong = strong_hash(window);
+                cands
+                    .iter()
+                    .find(|&&(hash, start)| hash == strong && &l[start..start + N] == window)
+                    .map(|&(_, start)| start)
+            });
+
+            if let Some(start) = found {
+                let mut l_start = start;
+                let mut r_start = pos;
+                while r_start > literal && l_start > 0 && l[l_start - 1] == r[r_start - 1] {
+                    l_start -= 1;
+                    r_start -= 1;
+                }
+                let mut len = pos + N - r_start;
+                while l_start + len < l.len()
+                    && r_start + len < r.len()
+                    && l[l_start + len] == r[r_start + len]
+                {
+                    len += 1;
+                }
+
+                match copies.last_mut() {
+                    Some((lo, ro, prev)) if *ro + *prev == r_start && *lo + *prev == l_start => {
+                        *prev += len
+                    }
+                    _ => copies.push((l_start, r_start, len)),
+                }
+
+                pos = r_start + len;
+                literal = pos;
+                sum = (pos + N <= r.len()).then(|| Rolling::new(&r[pos..pos + N]));
+            } else if pos + N < r.len() {
+                let mut next = cur;
+                next.roll(r[pos], r[pos + N]);
+                sum = Some(next);
+                pos += 1;
+            } else {
+                sum = None;
+            }
+        }
+
+        // The gaps between the copied left ranges, once sorted and merged
+        let mut ranges = copies
+            .iter()
+            .map(|&(lo, _, len)| (lo, lo + len))
+            .collect::<Vec<_>>();
+        ranges.sort_unstable();
+        let mut unused = Vec::new();
+        let mut idx = 0;
+        for (start, end) in ranges {
+            if start > idx {
+                unused.push(Chunk::new(l, idx, start - idx));
+            }
+            idx = idx.max(end);
+        }
+        if idx < l.len() {
+            unused.push(Chunk::new(l, idx, l.len() - idx));
+        }
+
+        let mut unused = unused.into_iter().peekable();
+        let mut out = Vec::new();
+        let mut r_pos = 0;
+        for (lo, ro, len) in copies {
+            while let Some(chunk) = unused.next_if(|c| c.offset < lo) {
+                out.push(DiffRes::Left(chunk));
+            }
+            if ro > r_pos {
+                out.push(DiffRes::Right(Chunk::new(r, r_pos, ro - r_pos)));
+            }
+            out.push(DiffRes::Both(
+                Chunk::new(l, lo, len),
+                Chunk::new(r, ro, len),
+            ));
+            r_pos = ro + len;
+        }
+        out.extend(unused.map(DiffRes::Left));
+        if r_pos < r.len() {
+            out.push(DiffRes::Right(Chunk::new(r, r_pos, r.len() - r_pos)));
+        }
+
+        out
+    }
+}
+
+impl<const N: usize> DiffPatch<[u8]> for BlockDiff<N> {}
+
+/// Rebuild the right side of a [`BlockDiff`] from the left side and the diff. Copies are resolved
+/// by offset, so `l` need not be the same buffer the diff borrows from, only have the same content.
+///
+/// # Panics
+///
+/// If a copied range lies outside `l`.
+pub fn apply(l: &[u8], diff: &[DiffRes<Chunk<'_>>]) -> Vec<u8> {
+    let mut out = Vec::new();
+    for d in diff {
+        match d {
+            DiffRes::Left(_) => (),
+            DiffRes::Both(chunk, _) => out.extend_from_slice(&l[chunk.offset..chunk.end()]),
+            DiffRes::Right(chunk) => out.extend_from_slice(chunk.data),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Diffable;
+
+    #[test]
+    fn test_block_diff() {
+        let a = b"0123456789abcdefghijklmnopqrstuv";
+        let b = b"0123456789ABCDefghijklmnopqrstuvwxyz";
+        let d = a[..].diff::<BlockDiff<4>>(b);
+
+        assert_eq!(
+            d,
+            vec![
+                DiffRes::Both(Chunk::new(a, 0, 10), Chunk::new(b, 0, 10)),
+                DiffRes::Left(Chunk::new(a, 10, 4)),
+                DiffRes::Right(Chunk::new(b, 10, 4)),
+                DiffRes::Both(Chunk::new(a, 14, 18), Chunk::new(b, 14, 18)),
+                DiffRes::Right(Chunk::new(b, 32, 4)),
+            ],
+        );
+        assert_eq!(apply(a, &d), b);
+    }
+
+    #[test]
+    fn test_block_moved() {
+        let a = b"AAAABBBBCCCCDDDD";
+        let b = b"CCCCDDDDxAAAABBBB";
+        let d = a[..].diff::<BlockDiff<4>>(b);
+
+        assert_eq!(
+            d,
+            vec![
+                DiffRes::Both(Chunk::new(a, 8, 8), Chunk::new(b, 0, 8)),
+                DiffRes::Right(Chunk::new(b, 8, 1)),
+                DiffRes::Both(Chunk::new(a, 0, 8), Chunk::new(b, 9, 8)),
+            ],
+        );
+        assert_eq!(apply(a, &d), b);
+    }
+
+    #[test]
+    fn test_block_repeated() {
+        let a = b"AAAABBBBCCCC";
+        let b = b"BBBBBBBB";
+        let d = a[..].diff::<BlockDiff<4>>(b);
+
+        assert_eq!(
+            d,
+            vec![
+                DiffRes::Left(Chunk::new(a, 0, 4)),
+                DiffRes::Both(Chunk::new(a, 4, 4), Chunk::new(b, 0, 4)),
+                DiffRes::Both(Chunk::new(a, 4, 4), Chunk::new(b, 4, 4)),
+                DiffRes::Left(Chunk::new(a, 8, 4)),
+            ],
+        );
+        assert_eq!(apply(a, &d), b);
+
+        // Every block of a long run shares a checksum, which mustn't make the scan quadratic
+        let a = vec![0u8; 1 << 20];
+        let b = [&a[..1 << 19], b"x", &a[1 << 19..]].concat();
+        let d = a[..].diff::<BlockDiff<4>>(&b[..]);
+        assert_eq!(apply(&a, &d), b);
+    }
+
+    #[test]
+    fn test_block_short() {
+        let a = b"abc";
+        let b = b"abd";
+        let d = a.diff::<BlockDiff<4>>(b);
+
+        assert_eq!(
+            d,
+            vec![
+                DiffRes::Left(Chunk::new(a, 0, 3)),
+                DiffRes::Right(Chunk::new(b, 0, 3)),
+            ],
+        );
+        assert_eq!(apply(a, &d), b);
+    }
+}
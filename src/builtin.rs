@@ -1,12 +1,64 @@
 use crate::algo::{DiffAlgo, DiffPatch};
 use crate::{algo, DiffRes, Diffable};
-use std::collections::HashSet;
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::fmt::Debug;
 use std::hash::Hash;
+#[cfg(feature = "unicode")]
+use std::marker::PhantomData;
+use std::rc::Rc;
+use std::sync::Arc;
 
 /// Generate a diff based on the longest common subsequence (McIlroy-Hunt) algorithm.
 pub struct LcsDiff;
 
+/// Generate a diff of an unordered collection, with the output sorted by value (or key, for maps).
+/// This makes the output deterministic, at the cost of sorting both inputs.
+pub struct SortedDiff;
+
+/// Generate a diff of a sequence, treating it as an unordered collection which may contain
+/// duplicates. Each distinct element is reported once, alongside the number of times it occurs in
+/// each input, in order of first appearance.
+pub struct MultisetDiff;
+
+/// Generate a diff treating values as atomic, which are either equal or entirely replaced.
+pub struct EqDiff;
+
+/// Generate a line diff like [`LcsDiff`], but compare lines after converting them to the Unicode
+/// normalization form `N`, and case-folding them if `FOLD_CASE` is set. Lines are returned exactly
+/// as they appear in the inputs.
+#[cfg(feature = "unicode")]
+pub struct NormalizedDiff<N = Nfc, const FOLD_CASE: bool = false>(PhantomData<N>);
+
+/// Generate a diff of a sequence of records, matching elements between the inputs by their
+/// [`Keyed::key`] rather than their position. Matched elements are diffed using their own
+/// [`Diffable`] implementation, so an edited record is reported as changed rather than removed and
+/// re-added.
+pub struct KeyedDiff;
+
+/// A type with an identity, used by [`KeyedDiff`] to match up elements that represent the same
+/// thing, even if their contents differ.
+pub trait Keyed {
+    /// The identity of a value. This may borrow from the value.
+    type Key<'a>: Eq + Hash
+    where
+        Self: 'a;
+
+    /// Get the identity of this value.
+    fn key(&self) -> Self::Key<'_>;
+
+    /// Whether this value is unchanged from `other`, a value with the same key. This compares them
+    /// with [`PartialEq`] by default, while derived impls check the derived diff has no changes, so
+    /// skipped fields are ignored.
+    fn is_unchanged(&self, other: &Self) -> bool
+    where
+        Self: PartialEq,
+    {
+        self == other
+    }
+}
+
 impl<T: PartialEq> DiffAlgo<[T]> for LcsDiff {
     type Diff<'a> = Vec<DiffRes<&'a [T]>>
     where
@@ -118,6 +170,353 @@ impl DiffAlgo<str> for algo::Default {
     }
 }
 
+/// A Unicode normalization form, used by [`NormalizedDiff`] to compare strings.
+#[cfg(feature = "unicode")]
+pub trait Normalization {
+    fn normalize(s: &str) -> String;
+}
+
+/// Compare strings exactly as they are.
+#[cfg(feature = "unicode")]
+pub struct Unnormalized;
+/// Canonical decomposition followed by canonical composition.
+#[cfg(feature = "unicode")]
+pub struct Nfc;
+/// Canonical decomposition.
+#[cfg(feature = "unicode")]
+pub struct Nfd;
+/// Compatibility decomposition followed by canonical composition.
+#[cfg(feature = "unicode")]
+pub struct Nfkc;
+/// Compatibility decomposition.
+#[cfg(feature = "unicode")]
+pub struct Nfkd;
+
+#[cfg(feature = "unicode")]
+mod normalization {
+    use super::{Nfc, Nfd, Nfkc, Nfkd, Normalization, Unnormalized};
+    use unicode_normalization::UnicodeNormalization;
+
+    impl Normalization for Unnormalized {
+        fn normalize(s: &str) -> String {
+            s.to_owned()
+        }
+    }
+
+    impl Normalization for Nfc {
+        fn normalize(s: &str) -> String {
+            s.nfc().collect()
+        }
+    }
+
+    impl Normalization for Nfd {
+        fn normalize(s: &str) -> String {
+            s.nfd().collect()
+        }
+    }
+
+    impl Normalization for Nfkc {
+        fn normalize(s: &str) -> String {
+            s.nfkc().collect()
+        }
+    }
+
+    impl Normalization for Nfkd {
+        fn normalize(s: &str) -> String {
+            s.nfkd().collect()
+        }
+    }
+}
+
+#[cfg(feature = "unicode")]
+impl<N: Normalization, const FOLD_CASE: bool> DiffAlgo<str> for NormalizedDiff<N, FOLD_CASE> {
+    type Diff<'a> = Vec<DiffRes<&'a str>>;
+
+    fn diff<'a>(l: &'a str, r: &'a str) -> Self::Diff<'a> {
+        let key = |line: &str| {
+            if FOLD_CASE {
+                // Folding may produce denormalized text, so decompose first and normalize after
+                N::normalize(&caseless::default_case_fold_str(&Nfd::normalize(line)))
+            } else {
+                N::normalize(line)
+            }
+        };
+        let l_lines = l.lines().collect::<Vec<_>>();
+        let r_lines = r.lines().collect::<Vec<_>>();
+        let l_keys = l_lines.iter().map(|line| key(line)).collect::<Vec<_>>();
+        let r_keys = r_lines.iter().map(|line| key(line)).collect::<Vec<_>>();
+
+        let (mut l_idx, mut r_idx) = (0, 0);
+        let mut out = Vec::new();
+        for chunk in LcsDiff::diff(&l_keys[..], &r_keys[..]) {
+            match chunk {
+                DiffRes::Left(removed) => {
+                    out.extend(
+                        l_lines[l_idx..][..removed.len()]
+                            .iter()
+                            .map(|&l| DiffRes::Left(l)),
+                    );
+                    l_idx += removed.len();
+                }
+                DiffRes::Both(both, _) => {
+                    let pairs = l_lines[l_idx..].iter().zip(&r_lines[r_idx..]);
+                    out.extend(pairs.take(both.len()).map(|(&l, &r)| DiffRes::Both(l, r)));
+                    l_idx += both.len();
+                    r_idx += both.len();
+                }
+                DiffRes::Right(added) => {
+                    out.extend(
+                        r_lines[r_idx..][..added.len()]
+                            .iter()
+                            .map(|&r| DiffRes::Right(r)),
+                    );
+                    r_idx += added.len();
+                }
+            }
+        }
+        // Match the handling of a trailing newline by `LcsDiff`
+        match (l.ends_with('\n'), r.ends_with('\n')) {
+            (true, true) => out.push(DiffRes::Both(&l[l.len()..], &r[r.len()..])),
+            (true, false) => out.push(DiffRes::Left(&l[l.len()..])),
+            (false, true) => out.push(DiffRes::Right(&r[r.len()..])),
+            (false, false) => (),
+        }
+        out
+    }
+}
+
+impl<T: Eq + Hash> DiffAlgo<[T]> for MultisetDiff {
+    type Diff<'a> = Vec<DiffRes<(&'a T, usize)>>
+    where
+        T: 'a;
+
+    fn diff<'a>(l: &'a [T], r: &'a [T]) -> Self::Diff<'a> {
+        let mut counts: Vec<(&T, usize, usize)> = Vec::new();
+        let mut seen: HashMap<&T, usize> = HashMap::new();
+        for (is_left, items) in [(true, l), (false, r)] {
+            for item in items {
+                let idx = *seen.entry(item).or_insert_with(|| {
+                    counts.push((item, 0, 0));
+                    counts.len() - 1
+                });
+                if is_left {
+                    counts[idx].1 += 1;
+                } else {
+                    counts[idx].2 += 1;
+                }
+            }
+        }
+
+        counts
+            .into_iter()
+            .map(|(item, lc, rc)| match (lc, rc) {
+                (lc, 0) => DiffRes::Left((item, lc)),
+                (0, rc) => DiffRes::Right((item, rc)),
+                (lc, rc) => DiffRes::Both((item, lc), (item, rc)),
+            })
+            .collect()
+    }
+}
+
+/// A single element of a [`KeyedDiff`].
+#[derive(Debug, PartialEq, Eq, Hash)]
+pub struct KeyedEntry<'a, T, D> {
+    /// Index of the element in the left input, if it is present there.
+    pub left: Option<usize>,
+    /// Index of the element in the right input, if it is present there.
+    pub right: Option<usize>,
+    /// Whether the element changed position relative to the other elements present in both inputs.
+    pub moved: bool,
+    /// The difference of the element itself.
+    pub entry: MapEntry<'a, T, D>,
+}
+
+/// An element of [`pair_lcs`].
+#[cfg(any(feature = "csv", feature = "json", feature = "toml", feature = "yaml"))]
+pub(crate) struct Paired<'a, T> {
+    /// The index of the element in the left input, or where it would be if only in the right.
+    pub left: usize,
+    /// The index of the element in the right input, or where it would be if only in the left.
+    pub right: usize,
+    pub res: DiffRes<&'a T>,
+}
+
+/// Diff two sequences by their longest common subsequence. A removal directly followed by an
+/// addition is treated as elements being modified in place, as far as they line up, and reported as
+/// [`DiffRes::Both`]. Elements of the common subsequence are only returned if `common` is set.
+#[cfg(any(feature = "csv", feature = "json", feature = "toml", feature = "yaml"))]
+pub(crate) fn pair_lcs<'a, T: PartialEq>(
+    l: &'a [T],
+    r: &'a [T],
+    common: bool,
+) -> Vec<Paired<'a, T>> {
+    let mut out = Vec::new();
+    let mut l_idx = 0;
+    let mut r_idx = 0;
+    let mut iter = LcsDiff::diff(l, r).into_iter().peekable();
+    while let Some(chunk) = iter.next() {
+        let (removed, added) = match chunk {
+            DiffRes::Both(both, _) if !common => {
+                l_idx += both.len();
+                r_idx += both.len();
+                continue;
+            }
+            DiffRes::Both(lb, rb) => (lb, rb),
+            DiffRes::Left(removed) => {
+                match iter.next_if(|next| matches!(next, DiffRes::Right(_))) {
+                    Some(DiffRes::Right(added)) => (removed, added),
+                    _ => (removed, &[][..]),
+                }
+            }
+            DiffRes::Right(added) => (&[][..], added),
+        };
+
+        let paired = usize::min(removed.len(), added.len());
+        let res = removed[..paired]
+            .iter()
+            .zip(added)
+            .map(|(lv, rv)| DiffRes::Both(lv, rv))
+            .chain(removed[paired..].iter().map(DiffRes::Left))
+            .chain(added[paired..].iter().map(DiffRes::Right));
+        for res in res {
+            let (left, right) = (l_idx, r_idx);
+            match res {
+                DiffRes::Left(_) => l_idx += 1,
+                DiffRes::Both(..) => {
+                    l_idx += 1;
+                    r_idx += 1;
+                }
+                DiffRes::Right(_) => r_idx += 1,
+            }
+            out.push(Paired { left, right, res });
+        }
+    }
+    out
+}
+
+/// Diff two sequences like [`pair_lcs`], returning only the changed elements. Removed elements are
+/// located by their index in the left input, and others by their index in the right.
+#[cfg(any(feature = "json", feature = "toml", feature = "yaml"))]
+pub(crate) fn diff_paired<'a, T: PartialEq>(
+    l: &'a [T],
+    r: &'a [T],
+) -> Vec<(usize, DiffRes<&'a T>)> {
+    pair_lcs(l, r, false)
+        .into_iter()
+        .map(|p| match p.res {
+            DiffRes::Left(_) => (p.left, p.res),
+            _ => (p.right, p.res),
+        })
+        .collect()
+}
+
+/// Find which items are part of a longest strictly increasing subsequence.
+fn increasing(items: &[usize]) -> Vec<bool> {
+    // tails[n] is the index of the smallest item ending an increasing run of length n + 1
+    let mut tails: Vec<usize> = Vec::new();
+    let mut prev = vec![None; items.len()];
+    for (idx, &item) in items.iter().enumerate() {
+        let len = tails.partition_point(|&t| items[t] < item);
+        prev[idx] = len.checked_sub(1).map(|p| tails[p]);
+        if len == tails.len() {
+            tails.push(idx);
+        } else {
+            tails[len] = idx;
+        }
+    }
+
+    let mut out = vec![false; items.len()];
+    let mut cur = tails.last().copied();
+    while let Some(idx) = cur {
+        out[idx] = true;
+        cur = prev[idx];
+    }
+    out
+}
+
+/// Matched elements are reported as [`MapEntry::Unchanged`] if [`Keyed::is_unchanged`].
+impl<T> DiffAlgo<[T]> for KeyedDiff
+where
+    T: Keyed + Diffable + PartialEq,
+    algo::Default: DiffAlgo<T::Item>,
+{
+    type Diff<'a> = Vec<KeyedEntry<'a, T, T::Diff<'a, algo::Default>>>
+    where
+        T: 'a;
+
+    fn diff<'a>(l: &'a [T], r: &'a [T]) -> Self::Diff<'a> {
+        let mut by_key: HashMap<T::Key<'a>, Vec<usize>> = HashMap::new();
+        for (idx, item) in l.iter().enumerate().rev() {
+            by_key.entry(item.key()).or_default().push(idx);
+        }
+
+        let matches = r
+            .iter()
+            .map(|item| by_key.get_mut(&item.key()).and_then(Vec::pop))
+            .collect::<Vec<_>>();
+        let matched = matches.iter().flatten().copied().collect::<Vec<_>>();
+        let in_order = increasing(&matched);
+        // Removals after the last match kept in order go straight after it, before any additions
+        let last = (0..r.len())
+            .filter(|&ridx| matches[ridx].is_some())
+            .zip(&in_order)
+            .filter_map(|(ridx, &kept)| kept.then_some(ridx))
+            .last();
+        let mut in_order = in_order.into_iter();
+
+        let mut unmatched = vec![true; l.len()];
+        matched.iter().for_each(|&idx| unmatched[idx] = false);
+        let mut removed = (0..l.len()).filter(|&idx| unmatched[idx]).peekable();
+
+        let mut out = Vec::new();
+        let removed_entry = |idx: usize| KeyedEntry {
+            left: Some(idx),
+            right: None,
+            moved: false,
+            entry: MapEntry::Removed(&l[idx]),
+        };
+        if last.is_none() {
+            out.extend(removed.by_ref().map(removed_entry));
+        }
+        for (ridx, lidx) in matches.into_iter().enumerate() {
+            let rv = &r[ridx];
+            let Some(lidx) = lidx else {
+                out.push(KeyedEntry {
+                    left: None,
+                    right: Some(ridx),
+                    moved: false,
+                    entry: MapEntry::Added(rv),
+                });
+                continue;
+            };
+
+            let moved = !in_order.next().unwrap_or(true);
+            if !moved {
+                while let Some(idx) = removed.next_if(|&idx| idx < lidx) {
+                    out.push(removed_entry(idx));
+                }
+            }
+            let lv = &l[lidx];
+            let entry = if lv.is_unchanged(rv) {
+                MapEntry::Unchanged(lv)
+            } else {
+                MapEntry::Changed(lv.diff::<algo::Default>(rv))
+            };
+            out.push(KeyedEntry {
+                left: Some(lidx),
+                right: Some(ridx),
+                moved,
+                entry,
+            });
+            if last == Some(ridx) {
+                out.extend(removed.by_ref().map(removed_entry));
+            }
+        }
+
+        out
+    }
+}
+
 impl<T: Eq + Hash> DiffAlgo<HashSet<T>> for LcsDiff {
     type Diff<'a> = HashSet<DiffRes<&'a T>>
     where
@@ -152,6 +551,239 @@ impl<T: Eq + Hash> Diffable for HashSet<T> {
     }
 }
 
+/// The difference of a single key between two maps. The type of [`Changed`](MapEntry::Changed)
+/// depends on the algorithm used to generate the diff.
+#[derive(Debug, PartialEq, Eq, Hash)]
+pub enum MapEntry<'a, V, D> {
+    /// The key only exists in the left map.
+    Removed(&'a V),
+    /// The key exists in both maps, with equal values.
+    Unchanged(&'a V),
+    /// The key exists in both maps, with differing values.
+    Changed(D),
+    /// The key only exists in the right map.
+    Added(&'a V),
+}
+
+fn diff_map<'a, K, V, D>(
+    l: &'a HashMap<K, V>,
+    r: &'a HashMap<K, V>,
+    mut changed: impl FnMut(&'a V, &'a V) -> D,
+) -> HashMap<&'a K, MapEntry<'a, V, D>>
+where
+    K: Eq + Hash,
+    V: PartialEq,
+{
+    let mut out = HashMap::new();
+    for (key, lv) in l {
+        let entry = match r.get(key) {
+            Some(rv) if lv == rv => MapEntry::Unchanged(lv),
+            Some(rv) => MapEntry::Changed(changed(lv, rv)),
+            None => MapEntry::Removed(lv),
+        };
+        out.insert(key, entry);
+    }
+    for (key, rv) in r {
+        if !l.contains_key(key) {
+            out.insert(key, MapEntry::Added(rv));
+        }
+    }
+    out
+}
+
+impl<K: Eq + Hash, V: PartialEq> DiffAlgo<HashMap<K, V>> for LcsDiff {
+    type Diff<'a> = HashMap<&'a K, MapEntry<'a, V, (&'a V, &'a V)>>
+    where
+        HashMap<K, V>: 'a;
+
+    fn diff<'a>(l: &'a HashMap<K, V>, r: &'a HashMap<K, V>) -> Self::Diff<'a> {
+        diff_map(l, r, |lv, rv| (lv, rv))
+    }
+}
+
+impl<K: Eq + Hash, V: PartialEq> DiffPatch<HashMap<K, V>> for LcsDiff {}
+
+/// Changed values are diffed using their own [`Diffable`] implementation.
+impl<K, V> DiffAlgo<HashMap<K, V>> for algo::Default
+where
+    K: Eq + Hash,
+    V: PartialEq + Diffable,
+    algo::Default: DiffAlgo<V::Item>,
+{
+    type Diff<'a> = HashMap<&'a K, MapEntry<'a, V, V::Diff<'a, algo::Default>>>
+    where
+        HashMap<K, V>: 'a;
+
+    fn diff<'a>(l: &'a HashMap<K, V>, r: &'a HashMap<K, V>) -> Self::Diff<'a> {
+        diff_map(l, r, |lv, rv| lv.diff::<algo::Default>(rv))
+    }
+}
+
+impl<K: Eq + Hash, V> Diffable for HashMap<K, V> {
+    type Diff<'a, A: DiffAlgo<Self::Item>> = A::Diff<'a>
+    where
+        Self: 'a;
+    type Item = HashMap<K, V>;
+
+    fn diff<'a, A: DiffAlgo<Self::Item>>(&'a self, other: &'a Self) -> Self::Diff<'a, A> {
+        A::diff(self, other)
+    }
+}
+
+/// Walk two sorted sequences in order, pairing up items with equal keys.
+fn merge_sorted<'a, T, K>(
+    l: impl IntoIterator<Item = T>,
+    r: impl IntoIterator<Item = T>,
+    key: impl Fn(&T) -> &'a K,
+) -> Vec<DiffRes<T>>
+where
+    K: Ord + ?Sized + 'a,
+{
+    let mut l = l.into_iter().peekable();
+    let mut r = r.into_iter().peekable();
+    let mut out = Vec::new();
+    loop {
+        let ord = match (l.peek(), r.peek()) {
+            (Some(lv), Some(rv)) => key(lv).cmp(key(rv)),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => break,
+        };
+        match ord {
+            Ordering::Less => out.extend(l.next().map(DiffRes::Left)),
+            Ordering::Greater => out.extend(r.next().map(DiffRes::Right)),
+            Ordering::Equal => {
+                out.extend(l.next().zip(r.next()).map(|(lv, rv)| DiffRes::Both(lv, rv)))
+            }
+        }
+    }
+    out
+}
+
+fn merge_map<'a, K, V, D>(
+    l: impl IntoIterator<Item = (&'a K, &'a V)>,
+    r: impl IntoIterator<Item = (&'a K, &'a V)>,
+    mut changed: impl FnMut(&'a V, &'a V) -> D,
+) -> Vec<(&'a K, MapEntry<'a, V, D>)>
+where
+    K: Ord + 'a,
+    V: PartialEq + 'a,
+{
+    merge_sorted(l, r, |(k, _)| *k)
+        .into_iter()
+        .map(|d| match d {
+            DiffRes::Left((k, lv)) => (k, MapEntry::Removed(lv)),
+            DiffRes::Both((k, lv), (_, rv)) if lv == rv => (k, MapEntry::Unchanged(lv)),
+            DiffRes::Both((k, lv), (_, rv)) => (k, MapEntry::Changed(changed(lv, rv))),
+            DiffRes::Right((k, rv)) => (k, MapEntry::Added(rv)),
+        })
+        .collect()
+}
+
+impl<T: Eq + Hash + Ord> DiffAlgo<HashSet<T>> for SortedDiff {
+    type Diff<'a> = Vec<DiffRes<&'a T>>
+    where
+        HashSet<T>: 'a;
+
+    fn diff<'a>(l: &'a HashSet<T>, r: &'a HashSet<T>) -> Self::Diff<'a> {
+        let mut l = l.iter().collect::<Vec<_>>();
+        let mut r = r.iter().collect::<Vec<_>>();
+        l.sort_unstable();
+        r.sort_unstable();
+        merge_sorted(l, r, |v| *v)
+    }
+}
+
+impl<T: Eq + Hash + Ord> DiffPatch<HashSet<T>> for SortedDiff {}
+
+impl<K: Eq + Hash + Ord, V: PartialEq> DiffAlgo<HashMap<K, V>> for SortedDiff {
+    type Diff<'a> = Vec<(&'a K, MapEntry<'a, V, (&'a V, &'a V)>)>
+    where
+        HashMap<K, V>: 'a;
+
+    fn diff<'a>(l: &'a HashMap<K, V>, r: &'a HashMap<K, V>) -> Self::Diff<'a> {
+        let mut l = l.iter().collect::<Vec<_>>();
+        let mut r = r.iter().collect::<Vec<_>>();
+        l.sort_unstable_by_key(|(k, _)| *k);
+        r.sort_unstable_by_key(|(k, _)| *k);
+        merge_map(l, r, |lv, rv| (lv, rv))
+    }
+}
+
+impl<K: Eq + Hash + Ord, V: PartialEq> DiffPatch<HashMap<K, V>> for SortedDiff {}
+
+impl<T: Ord> DiffAlgo<BTreeSet<T>> for LcsDiff {
+    type Diff<'a> = Vec<DiffRes<&'a T>>
+    where
+        BTreeSet<T>: 'a;
+
+    fn diff<'a>(l: &'a BTreeSet<T>, r: &'a BTreeSet<T>) -> Self::Diff<'a> {
+        merge_sorted(l, r, |v| *v)
+    }
+}
+
+impl<T: Ord> DiffPatch<BTreeSet<T>> for LcsDiff {}
+
+impl<T: Ord> DiffAlgo<BTreeSet<T>> for algo::Default {
+    type Diff<'a> = Vec<DiffRes<&'a T>>
+    where
+        BTreeSet<T>: 'a;
+
+    fn diff<'a>(l: &'a BTreeSet<T>, r: &'a BTreeSet<T>) -> Self::Diff<'a> {
+        LcsDiff::diff(l, r)
+    }
+}
+
+impl<T: Ord> Diffable for BTreeSet<T> {
+    type Diff<'a, A: DiffAlgo<Self::Item>> = A::Diff<'a>
+    where
+        Self: 'a;
+    type Item = BTreeSet<T>;
+
+    fn diff<'a, A: DiffAlgo<Self::Item>>(&'a self, other: &'a Self) -> Self::Diff<'a, A> {
+        A::diff(self, other)
+    }
+}
+
+impl<K: Ord, V: PartialEq> DiffAlgo<BTreeMap<K, V>> for LcsDiff {
+    type Diff<'a> = Vec<(&'a K, MapEntry<'a, V, (&'a V, &'a V)>)>
+    where
+        BTreeMap<K, V>: 'a;
+
+    fn diff<'a>(l: &'a BTreeMap<K, V>, r: &'a BTreeMap<K, V>) -> Self::Diff<'a> {
+        merge_map(l, r, |lv, rv| (lv, rv))
+    }
+}
+
+impl<K: Ord, V: PartialEq> DiffPatch<BTreeMap<K, V>> for LcsDiff {}
+
+/// Changed values are diffed using their own [`Diffable`] implementation.
+impl<K, V> DiffAlgo<BTreeMap<K, V>> for algo::Default
+where
+    K: Ord,
+    V: PartialEq + Diffable,
+    algo::Default: DiffAlgo<V::Item>,
+{
+    type Diff<'a> = Vec<(&'a K, MapEntry<'a, V, V::Diff<'a, algo::Default>>)>
+    where
+        BTreeMap<K, V>: 'a;
+
+    fn diff<'a>(l: &'a BTreeMap<K, V>, r: &'a BTreeMap<K, V>) -> Self::Diff<'a> {
+        merge_map(l, r, |lv, rv| lv.diff::<algo::Default>(rv))
+    }
+}
+
+impl<K: Ord, V> Diffable for BTreeMap<K, V> {
+    type Diff<'a, A: DiffAlgo<Self::Item>> = A::Diff<'a>
+    where
+        Self: 'a;
+    type Item = BTreeMap<K, V>;
+
+    fn diff<'a, A: DiffAlgo<Self::Item>>(&'a self, other: &'a Self) -> Self::Diff<'a, A> {
+        A::diff(self, other)
+    }
+}
+
 impl<T: PartialEq + Debug> Diffable for [T] {
     type Diff<'a, A: DiffAlgo<Self::Item>> = A::Diff<'a>
     where
@@ -171,15 +803,263 @@ impl Diffable for str {
     }
 }
 
+/// The difference between two atomic values.
+#[derive(Debug, PartialEq, Eq, Hash)]
+pub enum ValueDiff<'a, T: ?Sized> {
+    /// The values are equal.
+    Equal(&'a T),
+    /// The left value was replaced by the right value.
+    Changed(&'a T, &'a T),
+}
+
+impl<T: PartialEq + ?Sized> DiffAlgo<T> for EqDiff {
+    type Diff<'a> = ValueDiff<'a, T>
+    where
+        T: 'a;
+
+    fn diff<'a>(l: &'a T, r: &'a T) -> Self::Diff<'a> {
+        if l == r {
+            ValueDiff::Equal(l)
+        } else {
+            ValueDiff::Changed(l, r)
+        }
+    }
+}
+
+impl<T: PartialEq + ?Sized> DiffPatch<T> for EqDiff {}
+
+macro_rules! atomic_diffable {
+    ($($ty:ty),* $(,)?) => {
+        $(
+        impl DiffAlgo<$ty> for algo::Default {
+            type Diff<'a> = ValueDiff<'a, $ty>;
+
+            fn diff<'a>(l: &'a $ty, r: &'a $ty) -> Self::Diff<'a> {
+                EqDiff::diff(l, r)
+            }
+        }
+
+        impl Diffable for $ty {
+            type Diff<'a, A: DiffAlgo<Self::Item>> = A::Diff<'a>;
+            type Item = $ty;
+
+            fn diff<'a, A: DiffAlgo<Self::Item>>(&'a self, other: &'a Self) -> A::Diff<'a> {
+                A::diff(self, other)
+            }
+        }
+        )*
+    };
+}
+
+atomic_diffable!(bool, char, ());
+atomic_diffable!(u8, u16, u32, u64, u128, usize);
+atomic_diffable!(i8, i16, i32, i64, i128, isize);
+atomic_diffable!(f32, f64);
+
+impl<T: PartialEq + Debug> Diffable for Vec<T> {
+    type Diff<'a, A: DiffAlgo<Self::Item>> = A::Diff<'a>
+    where
+        Self: 'a;
+    type Item = [T];
+
+    fn diff<'a, A: DiffAlgo<Self::Item>>(&'a self, other: &'a Self) -> A::Diff<'a> {
+        A::diff(self, other)
+    }
+}
+
+/// Arrays are diffed as slices. Both arrays must have the same length, so arrays of different
+/// lengths are diffed by slicing them first, as in `a[..].diff(&b[..])`.
+impl<T: PartialEq + Debug, const N: usize> Diffable for [T; N] {
+    type Diff<'a, A: DiffAlgo<Self::Item>> = A::Diff<'a>
+    where
+        Self: 'a;
+    type Item = [T];
+
+    fn diff<'a, A: DiffAlgo<Self::Item>>(&'a self, other: &'a Self) -> A::Diff<'a> {
+        self[..].diff::<A>(&other[..])
+    }
+}
+
+impl Diffable for String {
+    type Diff<'a, A: DiffAlgo<Self::Item>> = A::Diff<'a>;
+    type Item = str;
+
+    fn diff<'a, A: DiffAlgo<Self::Item>>(&'a self, other: &'a Self) -> A::Diff<'a> {
+        A::diff(self, other)
+    }
+}
+
+macro_rules! deref_diffable {
+    ($($ty:ty),* $(,)?) => {
+        $(
+        impl<T: Diffable + ?Sized> Diffable for $ty {
+            type Diff<'a, A: DiffAlgo<Self::Item>> = T::Diff<'a, A>
+            where
+                Self: 'a;
+            type Item = T::Item;
+
+            fn diff<'a, A: DiffAlgo<Self::Item>>(&'a self, other: &'a Self) -> Self::Diff<'a, A> {
+                T::diff::<A>(self, other)
+            }
+        }
+        )*
+    };
+}
+
+deref_diffable!(&T, &mut T, Box<T>, Rc<T>, Arc<T>);
+
+impl<T: Diffable + ToOwned + ?Sized> Diffable for Cow<'_, T> {
+    type Diff<'a, A: DiffAlgo<Self::Item>> = T::Diff<'a, A>
+    where
+        Self: 'a;
+    type Item = T::Item;
+
+    fn diff<'a, A: DiffAlgo<Self::Item>>(&'a self, other: &'a Self) -> Self::Diff<'a, A> {
+        T::diff::<A>(self, other)
+    }
+}
+
+/// The difference between two values of an enum.
+#[derive(Debug, PartialEq, Eq, Hash)]
+pub enum VariantDiff<'a, T: ?Sized, D> {
+    /// Both values are the same variant, containing the difference of their contents.
+    Same(D),
+    /// The values are different variants.
+    Changed(&'a T, &'a T),
+}
+
+/// Contents of two [`Some`] values are diffed using their own [`Diffable`] implementation.
+impl<T> DiffAlgo<Option<T>> for algo::Default
+where
+    T: Diffable,
+    algo::Default: DiffAlgo<T::Item>,
+{
+    type Diff<'a> = VariantDiff<'a, Option<T>, Option<T::Diff<'a, algo::Default>>>
+    where
+        Option<T>: 'a;
+
+    fn diff<'a>(l: &'a Option<T>, r: &'a Option<T>) -> Self::Diff<'a> {
+        match (l, r) {
+            (Some(lv), Some(rv)) => VariantDiff::Same(Some(lv.diff::<algo::Default>(rv))),
+            (None, None) => VariantDiff::Same(None),
+            _ => VariantDiff::Changed(l, r),
+        }
+    }
+}
+
+impl<T> Diffable for Option<T> {
+    type Diff<'a, A: DiffAlgo<Self::Item>> = A::Diff<'a>
+    where
+        Self: 'a;
+    type Item = Option<T>;
+
+    fn diff<'a, A: DiffAlgo<Self::Item>>(&'a self, other: &'a Self) -> Self::Diff<'a, A> {
+        A::diff(self, other)
+    }
+}
+
+/// Contents of two [`Ok`] or two [`Err`] values are diffed using their own [`Diffable`]
+/// implementation.
+impl<T, E> DiffAlgo<Result<T, E>> for algo::Default
+where
+    T: Diffable,
+    E: Diffable,
+    algo::Default: DiffAlgo<T::Item> + DiffAlgo<E::Item>,
+{
+    type Diff<'a> =
+        VariantDiff<'a, Result<T, E>, Result<T::Diff<'a, algo::Default>, E::Diff<'a, algo::Default>>>
+    where
+        Result<T, E>: 'a;
+
+    fn diff<'a>(l: &'a Result<T, E>, r: &'a Result<T, E>) -> Self::Diff<'a> {
+        match (l, r) {
+            (Ok(lv), Ok(rv)) => VariantDiff::Same(Ok(lv.diff::<algo::Default>(rv))),
+            (Err(lv), Err(rv)) => VariantDiff::Same(Err(lv.diff::<algo::Default>(rv))),
+            _ => VariantDiff::Changed(l, r),
+        }
+    }
+}
+
+impl<T, E> Diffable for Result<T, E> {
+    type Diff<'a, A: DiffAlgo<Self::Item>> = A::Diff<'a>
+    where
+        Self: 'a;
+    type Item = Result<T, E>;
+
+    fn diff<'a, A: DiffAlgo<Self::Item>>(&'a self, other: &'a Self) -> Self::Diff<'a, A> {
+        A::diff(self, other)
+    }
+}
+
+macro_rules! tuple_diffable {
+    ($(($($name:ident $idx:tt),+)),* $(,)?) => {
+        $(
+        /// Each element is diffed using its own [`Diffable`] implementation.
+        impl<$($name),+> DiffAlgo<($($name,)+)> for algo::Default
+        where
+            $($name: Diffable,)+
+            $(algo::Default: DiffAlgo<$name::Item>,)+
+        {
+            type Diff<'a> = ($($name::Diff<'a, algo::Default>,)+)
+            where
+                ($($name,)+): 'a;
+
+            fn diff<'a>(l: &'a ($($name,)+), r: &'a ($($name,)+)) -> Self::Diff<'a> {
+                ($(l.$idx.diff::<algo::Default>(&r.$idx),)+)
+            }
+        }
+
+        impl<$($name),+> Diffable for ($($name,)+) {
+            type Diff<'a, A: DiffAlgo<Self::Item>> = A::Diff<'a>
+            where
+                Self: 'a;
+            type Item = ($($name,)+);
+
+            fn diff<'a, A: DiffAlgo<Self::Item>>(&'a self, other: &'a Self) -> Self::Diff<'a, A> {
+                A::diff(self, other)
+            }
+        }
+        )*
+    };
+}
+
+tuple_diffable!(
+    (T0 0),
+    (T0 0, T1 1),
+    (T0 0, T1 1, T2 2),
+    (T0 0, T1 1, T2 2, T3 3),
+    (T0 0, T1 1, T2 2, T3 3, T4 4),
+    (T0 0, T1 1, T2 2, T3 3, T4 4, T5 5),
+    (T0 0, T1 1, T2 2, T3 3, T4 4, T5 5, T6 6),
+    (T0 0, T1 1, T2 2, T3 3, T4 4, T5 5, T6 6, T7 7),
+);
+
 #[cfg(test)]
 mod tests {
+    use super::{
+        Keyed, KeyedDiff, KeyedEntry, LcsDiff, MapEntry, MultisetDiff, SortedDiff, VariantDiff,
+    };
+    use crate::algo::DiffAlgo;
     use crate::{algo, DiffRes, Diffable};
+    use std::borrow::Cow;
+    use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+    use std::rc::Rc;
+
+    impl Keyed for (&str, String) {
+        type Key<'a> = &'a str
+        where
+            Self: 'a;
+
+        fn key(&self) -> Self::Key<'_> {
+            self.0
+        }
+    }
 
     #[test]
     fn test_slice() {
         let a = [1, 2, 3, 4, 5, 6, 7, 8];
         let b = [1, 3, 4, 5, 2, 6, 7];
-        let d = a.diff::<algo::Default>(&b);
+        let d = a[..].diff::<algo::Default>(&b[..]);
 
         assert_eq!(
             d,
@@ -195,7 +1075,7 @@ mod tests {
 
         let a = [1, 2, 3, 4, 5];
         let b = [0, 1, 2, 3, 4, 5, 6, 7, 8];
-        let d = a.diff::<algo::Default>(&b);
+        let d = a[..].diff::<algo::Default>(&b[..]);
         assert_eq!(
             d,
             vec![
@@ -205,4 +1085,322 @@ mod tests {
             ],
         );
     }
+
+    #[test]
+    fn test_map() {
+        let a = HashMap::from([(1, "a"), (2, "b"), (3, "c")]);
+        let b = HashMap::from([(2, "b"), (3, "d"), (4, "e")]);
+        let d = a.diff::<LcsDiff>(&b);
+
+        assert_eq!(
+            d,
+            HashMap::from([
+                (&1, MapEntry::Removed(&"a")),
+                (&2, MapEntry::Unchanged(&"b")),
+                (&3, MapEntry::Changed((&"c", &"d"))),
+                (&4, MapEntry::Added(&"e")),
+            ]),
+        );
+    }
+
+    #[test]
+    fn test_map_nested() {
+        #[derive(Debug, PartialEq)]
+        struct Text(&'static str);
+
+        impl Diffable for Text {
+            type Diff<'a, A: DiffAlgo<Self::Item>> = A::Diff<'a>;
+            type Item = str;
+
+            fn diff<'a, A: DiffAlgo<Self::Item>>(&'a self, other: &'a Self) -> A::Diff<'a> {
+                A::diff(self.0, other.0)
+            }
+        }
+
+        let a = HashMap::from([("x", Text("1\n2\n3")), ("y", Text("4"))]);
+        let b = HashMap::from([("x", Text("1\n3"))]);
+        let d = crate::diff(&a, &b);
+
+        assert_eq!(
+            d,
+            HashMap::from([
+                (
+                    &"x",
+                    MapEntry::Changed(vec![
+                        DiffRes::Both("1", "1"),
+                        DiffRes::Left("2"),
+                        DiffRes::Both("3", "3"),
+                    ]),
+                ),
+                (&"y", MapEntry::Removed(&Text("4"))),
+            ]),
+        );
+    }
+
+    #[test]
+    fn test_sorted_set() {
+        let a = HashSet::from([5, 1, 3, 7]);
+        let b = HashSet::from([4, 3, 1, 8]);
+        let expected = vec![
+            DiffRes::Both(&1, &1),
+            DiffRes::Both(&3, &3),
+            DiffRes::Right(&4),
+            DiffRes::Left(&5),
+            DiffRes::Left(&7),
+            DiffRes::Right(&8),
+        ];
+
+        assert_eq!(a.diff::<SortedDiff>(&b), expected);
+
+        let a = a.into_iter().collect::<BTreeSet<_>>();
+        let b = b.into_iter().collect::<BTreeSet<_>>();
+        assert_eq!(crate::diff(&a, &b), expected);
+    }
+
+    #[test]
+    fn test_sorted_map() {
+        let a = BTreeMap::from([("a", "x\ny"), ("b", "z"), ("c", "w")]);
+        let b = BTreeMap::from([("a", "x"), ("c", "w"), ("d", "v")]);
+
+        assert_eq!(
+            a.diff::<LcsDiff>(&b),
+            vec![
+                (&"a", MapEntry::Changed((&"x\ny", &"x"))),
+                (&"b", MapEntry::Removed(&"z")),
+                (&"c", MapEntry::Unchanged(&"w")),
+                (&"d", MapEntry::Added(&"v")),
+            ],
+        );
+
+        let a = a.into_iter().collect::<HashMap<_, _>>();
+        let b = b.into_iter().collect::<HashMap<_, _>>();
+        assert_eq!(
+            a.diff::<SortedDiff>(&b),
+            vec![
+                (&"a", MapEntry::Changed((&"x\ny", &"x"))),
+                (&"b", MapEntry::Removed(&"z")),
+                (&"c", MapEntry::Unchanged(&"w")),
+                (&"d", MapEntry::Added(&"v")),
+            ],
+        );
+    }
+
+    #[test]
+    fn test_containers() {
+        let a = vec![1, 2, 3];
+        let b = vec![1, 3];
+        let expected = vec![
+            DiffRes::Both(&[1] as &[_], &[1]),
+            DiffRes::Left(&[2]),
+            DiffRes::Both(&[3], &[3]),
+        ];
+
+        assert_eq!(crate::diff(&a, &b), expected);
+        assert_eq!(
+            crate::diff(&[1, 2, 3], &[1, 4, 3]),
+            vec![
+                DiffRes::Both(&[1] as &[_], &[1]),
+                DiffRes::Left(&[2]),
+                DiffRes::Right(&[4]),
+                DiffRes::Both(&[3], &[3]),
+            ],
+        );
+        assert_eq!(crate::diff(&Box::new(&a), &Box::new(&b)), expected);
+        assert_eq!(crate::diff(&Rc::new(&a), &Rc::new(&b)), expected);
+        assert_eq!(crate::diff(&&a, &&b), expected);
+
+        let a = String::from("a\nb");
+        let b = String::from("a\nc");
+        let expected = vec![
+            DiffRes::Both("a", "a"),
+            DiffRes::Left("b"),
+            DiffRes::Right("c"),
+        ];
+
+        assert_eq!(crate::diff(&a, &b), expected);
+        assert_eq!(
+            crate::diff(&Cow::Borrowed(a.as_str()), &Cow::<str>::Owned(b.clone())),
+            expected,
+        );
+    }
+
+    #[test]
+    fn test_variants() {
+        let a = Some(String::from("a"));
+        let b = Some(String::from("b"));
+        assert_eq!(
+            crate::diff(&a, &b),
+            VariantDiff::Same(Some(vec![DiffRes::Left("a"), DiffRes::Right("b")])),
+        );
+        assert_eq!(crate::diff(&a, &None), VariantDiff::Changed(&a, &None));
+        assert_eq!(
+            crate::diff::<Option<String>>(&None, &None),
+            VariantDiff::Same(None),
+        );
+
+        let a: Result<String, Vec<u8>> = Ok(String::from("a"));
+        let b = Err(vec![1]);
+        assert_eq!(crate::diff(&a, &b), VariantDiff::Changed(&a, &b));
+        assert_eq!(
+            crate::diff(&b, &Err(vec![1, 2])),
+            VariantDiff::Same(Err(vec![
+                DiffRes::Both(&[1u8] as &[_], &[1]),
+                DiffRes::Right(&[2]),
+            ])),
+        );
+    }
+
+    #[test]
+    fn test_tuple() {
+        let a = (String::from("a"), vec![1, 2]);
+        let b = (String::from("a"), vec![2]);
+        assert_eq!(
+            crate::diff(&a, &b),
+            (
+                vec![DiffRes::Both("a", "a")],
+                vec![DiffRes::Left(&[1] as &[_]), DiffRes::Both(&[2], &[2])],
+            ),
+        );
+    }
+
+    #[test]
+    fn test_multiset() {
+        let a = ["b", "a", "b", "c", "b"];
+        let b = ["a", "d", "b", "a"];
+        let d = a[..].diff::<MultisetDiff>(&b[..]);
+
+        assert_eq!(
+            d,
+            vec![
+                DiffRes::Both((&"b", 3), (&"b", 1)),
+                DiffRes::Both((&"a", 1), (&"a", 2)),
+                DiffRes::Left((&"c", 1)),
+                DiffRes::Right((&"d", 1)),
+            ],
+        );
+    }
+
+    #[test]
+    fn test_keyed() {
+        let a = [
+            ("serde", String::from("1.0")),
+            ("image", String::from("0.24")),
+            ("diff", String::from("0.1")),
+            ("syn", String::from("2.0")),
+        ];
+        let b = [
+            ("syn", String::from("2.0")),
+            ("serde", String::from("1.0")),
+            ("image", String::from("0.25")),
+            ("quote", String::from("1.0")),
+        ];
+        let d = a[..].diff::<KeyedDiff>(&b[..]);
+
+        assert_eq!(
+            d,
+            vec![
+                KeyedEntry {
+                    left: Some(3),
+                    right: Some(0),
+                    moved: true,
+                    entry: MapEntry::Unchanged(&a[3]),
+                },
+                KeyedEntry {
+                    left: Some(0),
+                    right: Some(1),
+                    moved: false,
+                    entry: MapEntry::Unchanged(&a[0]),
+                },
+                KeyedEntry {
+                    left: Some(1),
+                    right: Some(2),
+                    moved: false,
+                    entry: MapEntry::Changed((
+                        vec![DiffRes::Both("image", "image")],
+                        vec![DiffRes::Left("0.24"), DiffRes::Right("0.25")],
+                    )),
+                },
+                KeyedEntry {
+                    left: Some(2),
+                    right: None,
+                    moved: false,
+                    entry: MapEntry::Removed(&a[2]),
+                },
+                KeyedEntry {
+                    left: None,
+                    right: Some(3),
+                    moved: false,
+                    entry: MapEntry::Added(&b[3]),
+                },
+            ],
+        );
+
+        // With nothing kept in order, every removal comes before the additions
+        let d = a[..2].diff::<KeyedDiff>(&b[3..]);
+        let sides = d.iter().map(|e| (e.left, e.right)).collect::<Vec<_>>();
+        assert_eq!(sides, vec![(Some(0), None), (Some(1), None), (None, Some(0))]);
+    }
+
+    #[cfg(feature = "unicode")]
+    #[test]
+    fn test_normalized() {
+        use super::{Nfkc, NormalizedDiff, Unnormalized};
+
+        // Precomposed and decomposed forms, as written on Linux and macOS
+        let a = "caf\u{e9}\nStra\u{df}e\nfi\n";
+        let b = "cafe\u{301}\nSTRASSE\n\u{fb01}\n";
+
+        let d = a.diff::<NormalizedDiff>(b);
+        assert_eq!(
+            d,
+            vec![
+                DiffRes::Both("caf\u{e9}", "cafe\u{301}"),
+                DiffRes::Left("Stra\u{df}e"),
+                DiffRes::Left("fi"),
+                DiffRes::Right("STRASSE"),
+                DiffRes::Right("\u{fb01}"),
+                DiffRes::Both("", ""),
+            ],
+        );
+
+        let d = a.diff::<NormalizedDiff<Nfkc, true>>(b);
+        assert!(d.iter().all(|res| matches!(res, DiffRes::Both(..))));
+        assert!(matches!(d[1], DiffRes::Both(l, r) if l == "Stra\u{df}e" && r == "STRASSE"));
+
+        let d = "A\n".diff::<NormalizedDiff<Unnormalized, true>>("a");
+        assert_eq!(d, vec![DiffRes::Both("A", "a"), DiffRes::Left("")]);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde() {
+        let a = vec![1, 2, 3];
+        let b = vec![1, 4, 3];
+        let d = crate::diff(&a, &b)
+            .into_iter()
+            .map(DiffRes::into_owned)
+            .collect::<Vec<_>>();
+
+        let json = serde_json::to_string(&d).unwrap();
+        assert_eq!(
+            json,
+            r#"[{"both":[[1],[1]]},{"left":[2]},{"right":[4]},{"both":[[3],[3]]}]"#
+        );
+        assert_eq!(
+            serde_json::from_str::<Vec<DiffRes<Vec<i32>>>>(&json).unwrap(),
+            d
+        );
+
+        // Escaped lines can't be borrowed from the JSON, so are read back owned
+        let d = "a\n\"b\""
+            .diff::<LcsDiff>("a\n\tc")
+            .into_iter()
+            .map(DiffRes::into_owned)
+            .collect::<Vec<_>>();
+        let json = serde_json::to_string(&d).unwrap();
+        assert_eq!(
+            serde_json::from_str::<Vec<DiffRes<String>>>(&json).unwrap(),
+            d
+        );
+    }
 }
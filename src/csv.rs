@@ -0,0 +1,435 @@
+//! Differences of CSV tables. Columns are matched by their header, so adding, removing or reordering
+//! columns doesn't change every line, and columns which are renamed without changing any values are
+//! detected. Rows are matched either by position, or by the value of a key column with
+//! [`CsvDiff::diff_by_key`], and changed rows report exactly which cells differ.
+
+use crate::algo::DiffAlgo;
+use crate::builtin::pair_lcs;
+use crate::{algo, DiffRes, Diffable};
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+
+/// Generate a diff of two tables, matching rows by their position.
+pub struct CsvDiff;
+
+/// The column used to match rows by [`CsvDiff::diff_by_key`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Key<'k> {
+    /// The column at this index in the left table, found in the right table by its header.
+    Index(usize),
+    /// The column with this header in both tables.
+    Name(&'k str),
+}
+
+/// A parsed CSV table. The first record is always taken as the headers.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Table {
+    pub headers: Vec<String>,
+    pub records: Vec<Record>,
+}
+
+/// A single record of a table, and the line it starts on.
+#[derive(Debug, Clone)]
+pub struct Record {
+    pub line: usize,
+    pub cells: Vec<String>,
+}
+
+/// Records are equal if their cells are, regardless of where they appear in the source.
+impl PartialEq for Record {
+    fn eq(&self, other: &Self) -> bool {
+        self.cells == other.cells
+    }
+}
+
+/// An error from parsing a CSV table.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Error {
+    pub line: usize,
+    pub msg: String,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.line, self.msg)
+    }
+}
+
+impl std::error::Error for Error {}
+
+fn convert_err(err: ::csv::Error) -> Error {
+    Error {
+        line: err.position().map_or(1, |pos| pos.line() as usize),
+        msg: err.to_string(),
+    }
+}
+
+/// Parse a CSV table. Every record must have the same number of cells as the headers.
+pub fn parse(src: &str) -> Result<Table, Error> {
+    let mut reader = ::csv::Reader::from_reader(src.as_bytes());
+    let headers = reader
+        .headers()
+        .map_err(convert_err)?
+        .iter()
+        .map(str::to_owned)
+        .collect();
+    let records = reader
+        .records()
+        .map(|record| {
+            let record = record.map_err(convert_err)?;
+            Ok(Record {
+                line: record.position().map_or(1, |pos| pos.line() as usize),
+                cells: record.iter().map(str::to_owned).collect(),
+            })
+        })
+        .collect::<Result<_, _>>()?;
+    Ok(Table { headers, records })
+}
+
+/// A column of a table, by its index and header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Column<'a> {
+    pub index: usize,
+    pub name: &'a str,
+}
+
+/// A cell which differs between two matched rows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cell<'a> {
+    /// The index of this cell's column in [`TableDiff::columns`].
+    pub column: usize,
+    pub left: &'a str,
+    pub right: &'a str,
+}
+
+/// A row which was removed, added, or changed. Changed rows list the cells that differ, in the
+/// order of [`TableDiff::columns`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct RowChange<'a> {
+    pub res: DiffRes<&'a Record>,
+    pub cells: Vec<Cell<'a>>,
+}
+
+/// The differences between two tables. Every column of either table is listed, in the order of the
+/// right table followed by any removed columns. A [`DiffRes::Both`] column is present in both
+/// tables, and has been renamed if the names differ. Only rows which changed are listed, removed
+/// rows first.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TableDiff<'a> {
+    pub columns: Vec<DiffRes<Column<'a>>>,
+    pub rows: Vec<RowChange<'a>>,
+}
+
+impl TableDiff<'_> {
+    /// Whether any column or row changed.
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+            && self.columns.iter().all(|col| match col {
+                DiffRes::Both(l, r) => l.name == r.name,
+                _ => false,
+            })
+    }
+}
+
+fn column(table: &Table, name: &str) -> Option<usize> {
+    table.headers.iter().position(|header| header == name)
+}
+
+/// The cells of a record in the given columns.
+fn project<'a>(record: &'a Record, columns: &[usize]) -> Vec<&'a str> {
+    columns.iter().map(|&idx| &*record.cells[idx]).collect()
+}
+
+/// Pair rows by their position, comparing them only on the shared columns. A removal directly
+/// followed by an addition is treated as rows being modified in place, as far as they line up.
+fn pair_by_position<'a>(
+    l: &'a Table,
+    r: &'a Table,
+    shared: &[(usize, usize)],
+) -> Vec<DiffRes<&'a Record>> {
+    let (l_cols, r_cols) = shared.iter().copied().unzip::<_, _, Vec<_>, Vec<_>>();
+    let l_rows = l
+        .records
+        .iter()
+        .map(|rec| project(rec, &l_cols))
+        .collect::<Vec<_>>();
+    let r_rows = r
+        .records
+        .iter()
+        .map(|rec| project(rec, &r_cols))
+        .collect::<Vec<_>>();
+
+    pair_lcs(&l_rows, &r_rows, true)
+        .into_iter()
+        .map(|p| match p.res {
+            DiffRes::Left(_) => DiffRes::Left(&l.records[p.left]),
+            DiffRes::Both(..) => DiffRes::Both(&l.records[p.left], &r.records[p.right]),
+            DiffRes::Right(_) => DiffRes::Right(&r.records[p.right]),
+        })
+        .collect()
+}
+
+/// Pair rows by the value of a key column, in order of occurrence for repeated keys.
+fn pair_by_key<'a>(l: &'a Table, r: &'a Table, key: (usize, usize)) -> Vec<DiffRes<&'a Record>> {
+    let mut by_key: HashMap<&str, VecDeque<usize>> = HashMap::new();
+    for (idx, rec) in l.records.iter().enumerate() {
+        by_key.entry(&rec.cells[key.0]).or_default().push_back(idx);
+    }
+
+    let mut unmatched = vec![true; l.records.len()];
+    let mut out = Vec::new();
+    for rec in &r.records {
+        match by_key
+            .get_mut(&*rec.cells[key.1])
+            .and_then(VecDeque::pop_front)
+        {
+            Some(idx) => {
+                unmatched[idx] = false;
+                out.push(DiffRes::Both(&l.records[idx], rec));
+            }
+            None => out.push(DiffRes::Right(rec)),
+        }
+    }
+    let removed = l
+        .records
+        .iter()
+        .zip(unmatched)
+        .filter(|(_, unmatched)| *unmatched)
+        .map(|(rec, _)| DiffRes::Left(rec));
+    removed.chain(out).collect()
+}
+
+fn diff_tables<'a>(l: &'a Table, r: &'a Table, key: Option<Key<'_>>) -> TableDiff<'a> {
+    let mut shared = r
+        .headers
+        .iter()
+        .enumerate()
+        .filter_map(|(r_idx, name)| Some((column(l, name)?, r_idx)))
+        .collect::<Vec<_>>();
+
+    let key = key
+        .and_then(|key| match key {
+            Key::Index(idx) => l.headers.get(idx).map(String::as_str),
+            Key::Name(name) => Some(name),
+        })
+        .and_then(|name| Some((column(l, name)?, column(r, name)?)));
+    let mut pairs = match key {
+        Some(key) => pair_by_key(l, r, key),
+        None => pair_by_position(l, r, &shared),
+    };
+    pairs.sort_by_key(|res| !matches!(res, DiffRes::Left(_)));
+
+    // A removed and an added column are a rename if they agree on every matched row. Columns with
+    // the same value on every row, such as empty ones, would agree with too much, so must vary.
+    let matched = pairs
+        .iter()
+        .filter_map(|res| match res {
+            DiffRes::Both(l, r) => Some((*l, *r)),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    let mut renamed = Vec::new();
+    if !matched.is_empty() {
+        for r_idx in 0..r.headers.len() {
+            if shared.iter().any(|&(_, idx)| idx == r_idx) {
+                continue;
+            }
+            let rename = (0..l.headers.len()).find(|&l_idx| {
+                let first = &matched[0].0.cells[l_idx];
+                !shared.iter().chain(&renamed).any(|&(idx, _)| idx == l_idx)
+                    && matched.iter().any(|(lr, _)| lr.cells[l_idx] != *first)
+                    && matched
+                        .iter()
+                        .all(|(lr, rr)| lr.cells[l_idx] == rr.cells[r_idx])
+            });
+            if let Some(l_idx) = rename {
+                renamed.push((l_idx, r_idx));
+            }
+        }
+    }
+    shared.extend(renamed);
+
+    let l_col = |index: usize| Column {
+        index,
+        name: &l.headers[index],
+    };
+    let r_col = |index: usize| Column {
+        index,
+        name: &r.headers[index],
+    };
+    let mut columns = (0..r.headers.len())
+        .map(
+            |r_idx| match shared.iter().find(|&&(_, idx)| idx == r_idx) {
+                Some(&(l_idx, _)) => DiffRes::Both(l_col(l_idx), r_col(r_idx)),
+                None => DiffRes::Right(r_col(r_idx)),
+            },
+        )
+        .collect::<Vec<_>>();
+    columns.extend(
+        (0..l.headers.len())
+            .filter(|&l_idx| !shared.iter().any(|&(idx, _)| idx == l_idx))
+            .map(|l_idx| DiffRes::Left(l_col(l_idx))),
+    );
+
+    let rows = pairs
+        .into_iter()
+        .filter_map(|res| {
+            let cells = match res {
+                DiffRes::Both(lr, rr) => columns
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(column, col)| match col {
+                        DiffRes::Both(lc, rc) if lr.cells[lc.index] != rr.cells[rc.index] => {
+                            Some(Cell {
+                                column,
+                                left: &lr.cells[lc.index],
+                                right: &rr.cells[rc.index],
+                            })
+                        }
+                        _ => None,
+                    })
+                    .collect::<Vec<_>>(),
+                _ => Vec::new(),
+            };
+            match res {
+                DiffRes::Both(..) if cells.is_empty() => None,
+                res => Some(RowChange { res, cells }),
+            }
+        })
+        .collect();
+
+    TableDiff { columns, rows }
+}
+
+impl DiffAlgo<Table> for CsvDiff {
+    type Diff<'a> = TableDiff<'a>;
+
+    fn diff<'a>(l: &'a Table, r: &'a Table) -> Self::Diff<'a> {
+        diff_tables(l, r, None)
+    }
+}
+
+impl CsvDiff {
+    /// Generate a diff of two tables, matching rows by the value in the `key` column. Repeated
+    /// keys are matched in order of occurrence. If either table doesn't have the column, this
+    /// matches rows by position instead.
+    pub fn diff_by_key<'a>(l: &'a Table, r: &'a Table, key: Key<'_>) -> TableDiff<'a> {
+        diff_tables(l, r, Some(key))
+    }
+}
+
+impl DiffAlgo<Table> for algo::Default {
+    type Diff<'a> = TableDiff<'a>;
+
+    fn diff<'a>(l: &'a Table, r: &'a Table) -> Self::Diff<'a> {
+        CsvDiff::diff(l, r)
+    }
+}
+
+impl Diffable for Table {
+    type Diff<'a, A: DiffAlgo<Self::Item>> = A::Diff<'a>;
+    type Item = Table;
+
+    fn diff<'a, A: DiffAlgo<Self::Item>>(&'a self, other: &'a Self) -> A::Diff<'a> {
+        A::diff(self, other)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn map<T, U>(res: &DiffRes<T>, f: impl Fn(&T) -> U) -> DiffRes<U> {
+        match res {
+            DiffRes::Left(l) => DiffRes::Left(f(l)),
+            DiffRes::Both(l, r) => DiffRes::Both(f(l), f(r)),
+            DiffRes::Right(r) => DiffRes::Right(f(r)),
+        }
+    }
+
+    #[test]
+    fn test_csv() {
+        let a = parse("id,name,price,stock\n1,apple,1.5,10\n2,pear,2,5\n3,plum,3,0\n").unwrap();
+        let b = parse("id,title,price,origin\n2,pear,2,FR\n1,apple,2,ES\n4,kiwi,5,NZ\n").unwrap();
+        let d = CsvDiff::diff_by_key(&a, &b, Key::Index(0));
+        assert_eq!(CsvDiff::diff_by_key(&a, &b, Key::Name("id")), d);
+
+        let columns = d
+            .columns
+            .iter()
+            .map(|col| map(col, |col| col.name))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            columns,
+            vec![
+                DiffRes::Both("id", "id"),
+                DiffRes::Both("name", "title"),
+                DiffRes::Both("price", "price"),
+                DiffRes::Right("origin"),
+                DiffRes::Left("stock"),
+            ],
+        );
+
+        let rows = d
+            .rows
+            .iter()
+            .map(|row| map(&row.res, |rec| rec.line))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            rows,
+            vec![DiffRes::Left(4), DiffRes::Both(2, 3), DiffRes::Right(4)]
+        );
+        assert_eq!(
+            d.rows[1].cells,
+            vec![Cell {
+                column: 2,
+                left: "1.5",
+                right: "2"
+            }],
+        );
+
+        let mut out = String::new();
+        crate::fmt::fmt_csv(d, &mut out).unwrap();
+        assert_eq!(
+            out,
+            "  | id | name -> title | price      | +origin | -stock |\n\
+             - | 3  | plum          | 3          |         | 0      |\n\
+             ~ | 1  | apple         | [1.5 -> 2] | ES      | 10     |\n\
+             + | 4  | kiwi          | 5          | NZ      |        |\n",
+        );
+    }
+
+    #[test]
+    fn test_csv_position() {
+        let a = parse("a,b\n1,2\n3,4\n5,6\n").unwrap();
+        let b = parse("b,a\n2,1\n0,0\n4,3\n6,7\n").unwrap();
+        let d = crate::diff(&a, &b);
+
+        let rows = d
+            .rows
+            .iter()
+            .map(|row| (map(&row.res, |rec| rec.line), row.cells.len()))
+            .collect::<Vec<_>>();
+        assert_eq!(rows, vec![(DiffRes::Right(3), 0), (DiffRes::Both(4, 5), 1)]);
+
+        assert!(crate::diff(&a, &a).is_empty());
+
+        // Columns with the same value on every row aren't renames
+        let a = parse("a,flag\n1,x\n2,x\n").unwrap();
+        let b = parse("a,other\n1,x\n3,x\n").unwrap();
+        let names = crate::diff(&a, &b)
+            .columns
+            .iter()
+            .map(|col| map(col, |col| col.name))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            names,
+            vec![
+                DiffRes::Both("a", "a"),
+                DiffRes::Right("other"),
+                DiffRes::Left("flag"),
+            ],
+        );
+        assert_eq!(parse("a,b\n1,2\n3\n").unwrap_err().line, 3);
+    }
+}
@@ -0,0 +1,252 @@
+//! Approximate differences of floating-point slices, so numeric outputs which only differ by
+//! rounding noise compare equal. Elements are compared using the tolerances of a [`Tolerance`]
+//! type, and each run of the output reports how far its elements deviate.
+
+use crate::algo::DiffAlgo;
+use crate::builtin::LcsDiff;
+use crate::DiffRes;
+use std::marker::PhantomData;
+
+/// Generate a diff of two float slices by their longest common subsequence, treating elements as
+/// equal if they are within the tolerances of `T`.
+pub struct ApproxDiff<T = DefaultTolerance>(PhantomData<T>);
+
+/// The tolerances used by [`ApproxDiff`]. Two values are equal if they are within any one of the
+/// tolerances. Implement this on your own type to pick different ones.
+pub trait Tolerance {
+    /// The largest absolute difference allowed.
+    const ABS: f64 = 0.0;
+    /// The largest difference allowed, relative to the larger magnitude of the two values.
+    const REL: f64 = 0.0;
+    /// The largest number of representable values allowed between the two values.
+    const ULPS: u64 = 0;
+    /// Whether NaN is equal to NaN. NaN is never equal to any other value.
+    const NAN_EQ: bool = true;
+}
+
+/// Allows a relative difference of `1e-9`, or 4 ULPs, and treats NaNs as equal.
+pub struct DefaultTolerance;
+
+impl Tolerance for DefaultTolerance {
+    const REL: f64 = 1e-9;
+    const ULPS: u64 = 4;
+}
+
+/// A floating-point type which can be diffed by [`ApproxDiff`].
+pub trait Float: Copy {
+    fn to_f64(self) -> f64;
+
+    /// The number of representable values between this and another value, treating `0.0` and
+    /// `-0.0` as the same value. Only meaningful if neither is NaN.
+    fn ulps(self, other: Self) -> u64;
+}
+
+impl Float for f32 {
+    fn to_f64(self) -> f64 {
+        self as f64
+    }
+
+    fn ulps(self, other: Self) -> u64 {
+        fn ordered(val: f32) -> i64 {
+            let bits = val.to_bits();
+            let mag = (bits & !(1 << 31)) as i64;
+            if bits >> 31 == 1 {
+                -mag
+            } else {
+                mag
+            }
+        }
+        ordered(self).abs_diff(ordered(other))
+    }
+}
+
+impl Float for f64 {
+    fn to_f64(self) -> f64 {
+        self
+    }
+
+    fn ulps(self, other: Self) -> u64 {
+        fn ordered(val: f64) -> i128 {
+            let bits = val.to_bits();
+            let mag = (bits & !(1 << 63)) as i128;
+            if bits >> 63 == 1 {
+                -mag
+            } else {
+                mag
+            }
+        }
+        let diff = ordered(self).abs_diff(ordered(other));
+        u64::try_from(diff).unwrap_or(u64::MAX)
+    }
+}
+
+/// Whether two values are equal within the tolerances of `T`.
+pub fn approx_eq<F: Float, T: Tolerance>(l: F, r: F) -> bool {
+    let (lf, rf) = (l.to_f64(), r.to_f64());
+    if lf.is_nan() || rf.is_nan() {
+        return T::NAN_EQ && lf.is_nan() && rf.is_nan();
+    }
+    if lf == rf {
+        return true;
+    }
+    let diff = (lf - rf).abs();
+    diff <= T::ABS || diff <= T::REL * f64::max(lf.abs(), rf.abs()) || l.ulps(r) <= T::ULPS
+}
+
+/// The absolute difference between two values. NaN deviates from NaN by nothing, and from any other
+/// value infinitely.
+fn deviation<F: Float>(l: F, r: F) -> f64 {
+    let (l, r) = (l.to_f64(), r.to_f64());
+    match (l.is_nan(), r.is_nan()) {
+        (true, true) => 0.0,
+        (false, false) if l == r => 0.0,
+        (false, false) => (l - r).abs(),
+        _ => f64::INFINITY,
+    }
+}
+
+fn max_deviation<F: Float>(l: &[F], r: &[F]) -> Option<f64> {
+    l.iter()
+        .zip(r)
+        .map(|(&l, &r)| deviation(l, r))
+        .reduce(f64::max)
+}
+
+/// A run of elements in an [`ApproxDiff`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Run<'a, F> {
+    pub res: DiffRes<&'a [F]>,
+    /// The largest absolute difference between the elements of this run, as far as they line up.
+    /// For a [`DiffRes::Both`] this is at most the tolerance. A removed run directly followed by an
+    /// added one is compared against it, and the two share the same deviation. Otherwise, there is
+    /// nothing to compare against and this is `None`.
+    pub max_deviation: Option<f64>,
+}
+
+struct Approx<F, T>(F, PhantomData<T>);
+
+impl<F: Float, T: Tolerance> PartialEq for Approx<F, T> {
+    fn eq(&self, other: &Self) -> bool {
+        approx_eq::<F, T>(self.0, other.0)
+    }
+}
+
+impl<F: Float, T: Tolerance> DiffAlgo<[F]> for ApproxDiff<T> {
+    type Diff<'a> = Vec<Run<'a, F>>
+    where
+        F: 'a;
+
+    fn diff<'a>(l: &'a [F], r: &'a [F]) -> Self::Diff<'a> {
+        let wrap = |s: &[F]| {
+            s.iter()
+                .map(|&v| Approx::<F, T>(v, PhantomData))
+                .collect::<Vec<_>>()
+        };
+        let (lw, rw) = (wrap(l), wrap(r));
+
+        // Map the chunks back to the original slices, by their lengths
+        let (mut l_idx, mut r_idx) = (0, 0);
+        let mut out = LcsDiff::diff(&lw[..], &rw[..])
+            .into_iter()
+            .map(|chunk| {
+                let res = match chunk {
+                    DiffRes::Left(removed) => {
+                        l_idx += removed.len();
+                        DiffRes::Left(&l[l_idx - removed.len()..l_idx])
+                    }
+                    DiffRes::Both(both, _) => {
+                        l_idx += both.len();
+                        r_idx += both.len();
+                        DiffRes::Both(&l[l_idx - both.len()..l_idx], &r[r_idx - both.len()..r_idx])
+                    }
+                    DiffRes::Right(added) => {
+                        r_idx += added.len();
+                        DiffRes::Right(&r[r_idx - added.len()..r_idx])
+                    }
+                };
+                let max_deviation = match res {
+                    DiffRes::Both(l, r) => max_deviation(l, r),
+                    _ => None,
+                };
+                Run { res, max_deviation }
+            })
+            .collect::<Vec<_>>();
+
+        for idx in 1..out.len() {
+            if let (DiffRes::Left(l) | DiffRes::Right(l), DiffRes::Left(r) | DiffRes::Right(r)) =
+                (&out[idx - 1].res, &out[idx].res)
+            {
+                let dev = max_deviation(l, r);
+                out[idx - 1].max_deviation = dev;
+                out[idx].max_deviation = dev;
+            }
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Diffable;
+
+    #[test]
+    fn test_approx() {
+        let a = [1.0, 0.1 + 0.2, 2.0, 3.0, 4.0];
+        let b = [1.0, 0.3, 2.5, 3.0, 4.0, 5.0];
+        let d = a[..].diff::<ApproxDiff>(&b[..]);
+
+        assert_eq!(
+            d,
+            vec![
+                Run {
+                    res: DiffRes::Both(&a[..2], &b[..2]),
+                    max_deviation: Some((0.1 + 0.2) - 0.3),
+                },
+                Run {
+                    res: DiffRes::Left(&a[2..3]),
+                    max_deviation: Some(0.5),
+                },
+                Run {
+                    res: DiffRes::Right(&b[2..3]),
+                    max_deviation: Some(0.5),
+                },
+                Run {
+                    res: DiffRes::Both(&a[3..], &b[3..5]),
+                    max_deviation: Some(0.0),
+                },
+                Run {
+                    res: DiffRes::Right(&b[5..]),
+                    max_deviation: None,
+                },
+            ],
+        );
+    }
+
+    #[test]
+    fn test_approx_tolerance() {
+        struct Abs;
+        impl Tolerance for Abs {
+            const ABS: f64 = 0.01;
+            const NAN_EQ: bool = false;
+        }
+
+        assert!(approx_eq::<f32, DefaultTolerance>(f32::NAN, f32::NAN));
+        assert!(!approx_eq::<f32, Abs>(f32::NAN, f32::NAN));
+        assert!(approx_eq::<f32, DefaultTolerance>(0.0, -0.0));
+        assert!(approx_eq::<f32, DefaultTolerance>(
+            1.0,
+            1.0 + 4.0 * f32::EPSILON
+        ));
+        assert!(!approx_eq::<f32, DefaultTolerance>(
+            1.0,
+            1.0 + 5.0 * f32::EPSILON
+        ));
+        assert!(approx_eq::<f64, Abs>(1.0, 1.005));
+        assert!(!approx_eq::<f64, Abs>(f64::INFINITY, f64::NEG_INFINITY));
+
+        let d = [f64::NAN, 1.0].diff::<ApproxDiff<Abs>>(&[0.0, 1.0]);
+        assert_eq!(d[0].max_deviation, Some(f64::INFINITY));
+        assert_eq!(d[2].max_deviation, Some(0.0));
+    }
+}
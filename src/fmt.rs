@@ -78,3 +78,289 @@ pub fn fmt_bytes<W: Write>(diff: Vec<DiffRes<&[u8]>>, w: &mut W) -> fmt::Result
     }
     Ok(())
 }
+
+/// Format a YAML diff, one change per line as written by its [`Display`](fmt::Display) impl,
+/// under a header for each document with changes.
+#[cfg(feature = "yaml")]
+pub fn fmt_yaml<W: Write>(diff: Vec<crate::yaml::Change<'_>>, w: &mut W) -> fmt::Result {
+    let mut document = None;
+    for d in &diff {
+        if document != Some(d.document) {
+            writeln!(w, "@@ document {} @@", d.document)?;
+            document = Some(d.document);
+        }
+        writeln!(w, "{}", d)?;
+    }
+    Ok(())
+}
+
+/// Format a table diff as an aligned table of the changed rows. Headers of removed and added
+/// columns are marked with `-` and `+`, and renamed ones shown as `old -> new`. Changed cells are
+/// shown as `[old -> new]`.
+#[cfg(feature = "csv")]
+pub fn fmt_csv<W: Write>(diff: crate::csv::TableDiff<'_>, w: &mut W) -> fmt::Result {
+    let header = diff.columns.iter()
+        .map(|col| match col {
+            DiffRes::Left(l) => format!("-{}", l.name),
+            DiffRes::Both(l, r) if l.name != r.name => format!("{} -> {}", l.name, r.name),
+            DiffRes::Both(_, r) => r.name.to_owned(),
+            DiffRes::Right(r) => format!("+{}", r.name),
+        })
+        .collect::<Vec<_>>();
+
+    let mut rows = vec![(' ', header)];
+    for row in &diff.rows {
+        let cells = diff.columns.iter()
+            .enumerate()
+            .map(|(idx, col)| match (&row.res, col) {
+                (DiffRes::Left(rec), DiffRes::Left(c) | DiffRes::Both(c, _)) => rec.cells[c.index].clone(),
+                (DiffRes::Right(rec), DiffRes::Right(c) | DiffRes::Both(_, c)) => rec.cells[c.index].clone(),
+                (DiffRes::Both(rec, _), DiffRes::Left(c)) => rec.cells[c.index].clone(),
+                (DiffRes::Both(_, rec), DiffRes::Right(c) | DiffRes::Both(_, c)) => {
+                    match row.cells.iter().find(|cell| cell.column == idx) {
+                        Some(cell) => format!("[{} -> {}]", cell.left, cell.right),
+                        None => rec.cells[c.index].clone(),
+                    }
+                },
+                _ => String::new(),
+            })
+            .collect();
+        let marker = match row.res {
+            DiffRes::Left(_) => '-',
+            DiffRes::Both(..) => '~',
+            DiffRes::Right(_) => '+',
+        };
+        rows.push((marker, cells));
+    }
+
+    let widths = (0..diff.columns.len())
+        .map(|idx| rows.iter().map(|(_, cells)| cells[idx].chars().count()).max().unwrap_or(0))
+        .collect::<Vec<_>>();
+    for (marker, cells) in &rows {
+        write!(w, "{}", marker)?;
+        for (cell, width) in cells.iter().zip(&widths) {
+            write!(w, " | {:width$}", cell, width = width)?;
+        }
+        writeln!(w, " |")?;
+    }
+    Ok(())
+}
+
+/// Format the summary statistics of a numeric diff, including the location of each of the worst
+/// changes.
+#[cfg(feature = "num")]
+pub fn fmt_stats<W: Write, I: Debug>(stats: &crate::num::Stats<I>, w: &mut W) -> fmt::Result {
+    writeln!(w, "compared {} elements, {} changed, {} NaN", stats.count, stats.changed, stats.nan)?;
+    writeln!(w, "max abs error:  {}", stats.max_abs_error)?;
+    writeln!(w, "mean abs error: {}", stats.mean_abs_error)?;
+    writeln!(w, "rms:            {}", stats.rms)?;
+    if !stats.worst.is_empty() {
+        writeln!(w, "worst:")?;
+    }
+    for d in &stats.worst {
+        writeln!(w, "  at {:?}: {} -> {} ({:+})", d.index, d.left, d.right, d.delta)?;
+    }
+    Ok(())
+}
+
+fn write_json_str<W: Write>(w: &mut W, s: &str) -> fmt::Result {
+    w.write_char('"')?;
+    for c in s.chars() {
+        match c {
+            '"' => w.write_str("\\\"")?,
+            '\\' => w.write_str("\\\\")?,
+            '\n' => w.write_str("\\n")?,
+            '\r' => w.write_str("\\r")?,
+            '\t' => w.write_str("\\t")?,
+            c if (c as u32) < 0x20 => write!(w, "\\u{:04x}", c as u32)?,
+            c => w.write_char(c)?,
+        }
+    }
+    w.write_char('"')
+}
+
+fn write_json_pos<W: Write>(w: &mut W, pos: Option<usize>) -> fmt::Result {
+    match pos {
+        Some(pos) => write!(w, "{}", pos),
+        None => write!(w, "null"),
+    }
+}
+
+/// Write a JSON report of a diff of individual entries, as described by [`fmt_string_json`].
+fn fmt_json<T: ?Sized, W: Write>(
+    diff: Vec<DiffRes<&T>>,
+    base: usize,
+    context: usize,
+    w: &mut W,
+    mut content: impl FnMut(&mut W, &T) -> fmt::Result,
+) -> fmt::Result {
+    // The position of each entry in the left and right inputs, or of the entry it would be inserted
+    // before if it's not present in one
+    let (mut l_pos, mut r_pos) = (base, base);
+    let positions = diff.iter()
+        .map(|d| {
+            let pos = (l_pos, r_pos);
+            l_pos += !matches!(d, DiffRes::Right(_)) as usize;
+            r_pos += !matches!(d, DiffRes::Left(_)) as usize;
+            pos
+        })
+        .collect::<Vec<_>>();
+
+    // Changed entries, along with their context, grouped into hunks where they overlap or touch
+    let mut hunks: Vec<(usize, usize)> = Vec::new();
+    for (idx, d) in diff.iter().enumerate() {
+        if let DiffRes::Both(..) = d {
+            continue;
+        }
+        let start = idx.saturating_sub(context);
+        let end = usize::min(idx + context + 1, diff.len());
+        match hunks.last_mut() {
+            Some(last) if last.1 >= start => last.1 = end,
+            _ => hunks.push((start, end)),
+        }
+    }
+
+    let count = |f: fn(&DiffRes<&T>) -> bool| diff.iter().filter(|d| f(d)).count();
+    write!(
+        w,
+        "{{\"summary\":{{\"added\":{},\"removed\":{},\"unchanged\":{},\"hunks\":{}}},\"hunks\":[",
+        count(|d| matches!(d, DiffRes::Right(_))),
+        count(|d| matches!(d, DiffRes::Left(_))),
+        count(|d| matches!(d, DiffRes::Both(..))),
+        hunks.len(),
+    )?;
+    for (hunk_idx, &(start, end)) in hunks.iter().enumerate() {
+        if hunk_idx != 0 {
+            w.write_char(',')?;
+        }
+        let entries = &diff[start..end];
+        let (l_start, r_start) = positions[start];
+        write!(
+            w,
+            "{{\"left_start\":{},\"left_len\":{},\"right_start\":{},\"right_len\":{},\"entries\":[",
+            l_start,
+            entries.iter().filter(|d| !matches!(d, DiffRes::Right(_))).count(),
+            r_start,
+            entries.iter().filter(|d| !matches!(d, DiffRes::Left(_))).count(),
+        )?;
+        for (idx, (d, &(lp, rp))) in entries.iter().zip(&positions[start..end]).enumerate() {
+            if idx != 0 {
+                w.write_char(',')?;
+            }
+            let (op, item, l, r) = match d {
+                DiffRes::Left(l) => ("removed", *l, Some(lp), None),
+                DiffRes::Both(l, _) => ("unchanged", *l, Some(lp), Some(rp)),
+                DiffRes::Right(r) => ("added", *r, None, Some(rp)),
+            };
+            write!(w, "{{\"op\":\"{}\",\"left\":", op)?;
+            write_json_pos(w, l)?;
+            write!(w, ",\"right\":")?;
+            write_json_pos(w, r)?;
+            write!(w, ",\"content\":")?;
+            content(w, item)?;
+            w.write_char('}')?;
+        }
+        write!(w, "]}}")?;
+    }
+    write!(w, "]}}")
+}
+
+/// Format a line diff as a JSON report, for consumption by other tools. Changed lines are grouped
+/// into hunks, with up to `context` unchanged lines around each change. The report is a single
+/// object:
+///
+/// - `summary`: an object of `added`, `removed` and `unchanged`, the number of lines of each kind
+///   in the whole diff, and `hunks`, the number of hunks.
+/// - `hunks`: an array of objects, each with:
+///   - `left_start` and `right_start`: the first line of the hunk in each input. If the hunk
+///     contains no lines of an input, this is the line it would be inserted before.
+///   - `left_len` and `right_len`: the number of lines of the hunk in each input.
+///   - `entries`: an array of objects with `op` (`"removed"`, `"unchanged"` or `"added"`), `left`
+///     and `right` (the line in each input, or `null` if not present there), and `content` (the
+///     text of the line, from the left input if unchanged).
+///
+/// Lines are numbered from 1.
+pub fn fmt_string_json<W: Write>(diff: Vec<DiffRes<&str>>, context: usize, w: &mut W) -> fmt::Result {
+    fmt_json(diff, 1, context, w, write_json_str)
+}
+
+/// Format a slice diff as a JSON report, in the same form as [`fmt_string_json`]. Each element is
+/// an entry, positioned by its index from 0, with its [`Debug`] representation as the `content`.
+pub fn fmt_slice_json<T: Debug, W: Write>(diff: Vec<DiffRes<&[T]>>, context: usize, w: &mut W) -> fmt::Result {
+    let diff = diff.into_iter().flat_map(split_chunk).collect();
+    fmt_json(diff, 0, context, w, |w, item: &T| write_json_str(w, &format!("{:?}", item)))
+}
+
+/// Format a byte diff as a JSON report, in the same form as [`fmt_string_json`]. Each byte is an
+/// entry, positioned by its offset from 0, with its value as a number for the `content`.
+pub fn fmt_bytes_json<W: Write>(diff: Vec<DiffRes<&[u8]>>, context: usize, w: &mut W) -> fmt::Result {
+    let diff = diff.into_iter().flat_map(split_chunk).collect();
+    fmt_json(diff, 0, context, w, |w, byte: &u8| write!(w, "{}", byte))
+}
+
+/// Split a chunk of a slice diff into its individual elements.
+fn split_chunk<T>(chunk: DiffRes<&[T]>) -> Vec<DiffRes<&T>> {
+    match chunk {
+        DiffRes::Left(l) => l.iter().map(DiffRes::Left).collect(),
+        DiffRes::Both(l, r) => l.iter().zip(r).map(|(l, r)| DiffRes::Both(l, r)).collect(),
+        DiffRes::Right(r) => r.iter().map(DiffRes::Right).collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builtin::LcsDiff;
+    use crate::Diffable;
+
+    #[test]
+    fn test_json_report() {
+        let d = "a\nb\n\"c\"\nd\ne\nf".diff::<LcsDiff>("a\nB\n\"c\"\nd\ne\nf\ng");
+        let mut out = String::new();
+        fmt_string_json(d, 1, &mut out).unwrap();
+        assert_eq!(
+            out,
+            concat!(
+                r#"{"summary":{"added":2,"removed":1,"unchanged":5,"hunks":2},"hunks":["#,
+                r#"{"left_start":1,"left_len":3,"right_start":1,"right_len":3,"entries":["#,
+                r#"{"op":"unchanged","left":1,"right":1,"content":"a"},"#,
+                r#"{"op":"removed","left":2,"right":null,"content":"b"},"#,
+                r#"{"op":"added","left":null,"right":2,"content":"B"},"#,
+                r#"{"op":"unchanged","left":3,"right":3,"content":"\"c\""}]},"#,
+                r#"{"left_start":6,"left_len":1,"right_start":6,"right_len":2,"entries":["#,
+                r#"{"op":"unchanged","left":6,"right":6,"content":"f"},"#,
+                r#"{"op":"added","left":null,"right":7,"content":"g"}]}]}"#,
+            ),
+        );
+
+        let d = [1u8, 2, 3][..].diff::<LcsDiff>(&[1, 3][..]);
+        let mut out = String::new();
+        fmt_bytes_json(d, 0, &mut out).unwrap();
+        assert_eq!(
+            out,
+            concat!(
+                r#"{"summary":{"added":0,"removed":1,"unchanged":2,"hunks":1},"hunks":["#,
+                r#"{"left_start":1,"left_len":1,"right_start":1,"right_len":0,"entries":["#,
+                r#"{"op":"removed","left":1,"right":null,"content":2}]}]}"#,
+            ),
+        );
+    }
+
+    #[test]
+    fn test_slice_json_report() {
+        let d = ["a", "b", "c"][..].diff::<LcsDiff>(&["a", "x", "c", "d"][..]);
+        let mut out = String::new();
+        fmt_slice_json(d, 0, &mut out).unwrap();
+        assert_eq!(
+            out,
+            concat!(
+                r#"{"summary":{"added":2,"removed":1,"unchanged":2,"hunks":2},"hunks":["#,
+                r#"{"left_start":1,"left_len":1,"right_start":1,"right_len":1,"entries":["#,
+                r#"{"op":"removed","left":1,"right":null,"content":"\"b\""},"#,
+                r#"{"op":"added","left":null,"right":1,"content":"\"x\""}]},"#,
+                r#"{"left_start":3,"left_len":0,"right_start":3,"right_len":1,"entries":["#,
+                r#"{"op":"added","left":null,"right":3,"content":"\"d\""}]}]}"#,
+            ),
+        );
+    }
+}
@@ -70,7 +70,12 @@ pub trait PixelExt: Pixel {
                 idx += 1;
                 out
             },
-            |_| g(*self.alpha().unwrap(), *other.alpha().unwrap()),
+            |_| {
+                g(
+                    *PixelExt::alpha(self).unwrap(),
+                    *PixelExt::alpha(other).unwrap(),
+                )
+            },
         );
         new
     }
@@ -203,8 +208,8 @@ where
                 let pl = l.get_pixel_checked(w, h).unwrap_or(zeroed);
                 let pr = r.get_pixel_checked(w, h).unwrap_or(zeroed);
                 if pl.channels_no_alpha() == pr.channels_no_alpha()
-                    || (pl.alpha() == Some(&P::Subpixel::zero())
-                        && pr.alpha() == Some(&P::Subpixel::zero()))
+                    || (PixelExt::alpha(pl) == Some(&P::Subpixel::zero())
+                        && PixelExt::alpha(pr) == Some(&P::Subpixel::zero()))
                 {
                     out[(w, h)] = *pl;
                 } else {
@@ -256,8 +261,8 @@ where
                 let pr = r.get_pixel_checked(w, h).unwrap_or(zeroed);
                 // TODO: Try to judge 'how different' the pixels are
 
-                let la = pl.alpha().copied().unwrap_or(Zero::zero());
-                let ra = pr.alpha().copied().unwrap_or(Zero::zero());
+                let la = PixelExt::alpha(pl).copied().unwrap_or(Zero::zero());
+                let ra = PixelExt::alpha(pr).copied().unwrap_or(Zero::zero());
                 match la.partial_cmp(&ra).unwrap() {
                     Ordering::Equal if la == Zero::zero() => {
                         out[(w, h)] = *pl;
@@ -291,12 +296,21 @@ mod sealed {
 
     pub trait DiffPixel: Pixel {
         type Diff;
+
+        /// Subtract `l` from `r` in each channel, taking a missing `l` as zero.
+        fn sub(l: Option<&Self>, r: &Self) -> Self::Diff;
     }
 
     macro_rules! diff_pixels {
         (@ $pixel:ident, $ty:ty, $bigger:ty) => {
             impl DiffPixel for $pixel<$ty> {
                 type Diff = [$bigger; <$pixel<$ty> as Pixel>::CHANNEL_COUNT as usize];
+
+                fn sub(l: Option<&Self>, r: &Self) -> Self::Diff {
+                    std::array::from_fn(|c| {
+                        r.0[c] as $bigger - l.map_or(0 as $bigger, |l| l.0[c] as $bigger)
+                    })
+                }
             }
         };
         ($($ty:ty => $bigger:ty),* $(,)?) => {
@@ -325,6 +339,10 @@ mod sealed {
 
 use sealed::DiffPixel;
 
+/// Generate the change in each channel of each pixel, as the right value minus the left, in a wider
+/// type so it can't overflow. The diff has a row for each row of the right image, each with a
+/// change for each of its pixels. Pixels outside of the left image are taken as zero, so the right
+/// image can be rebuilt from the left and the diff.
 pub struct PixelPatch;
 
 impl<P, C> DiffAlgo<ImageBuffer<P, C>> for PixelPatch
@@ -337,7 +355,13 @@ where
         ImageBuffer<P, C>: 'a;
 
     fn diff<'a>(l: &'a ImageBuffer<P, C>, r: &'a ImageBuffer<P, C>) -> Self::Diff<'a> {
-        todo!()
+        (0..r.height())
+            .map(|h| {
+                (0..r.width())
+                    .map(|w| P::sub(l.get_pixel_checked(w, h), r.get_pixel(w, h)))
+                    .collect()
+            })
+            .collect()
     }
 }
 
@@ -348,6 +372,115 @@ where
 {
 }
 
+/// Generate a summary of how two images differ, independent of how the difference is visualized.
+/// A pixel has changed if any of its channels differ, unless it is fully transparent in both
+/// images. Pixels outside of one image are compared as if they were zero.
+pub struct Metadata;
+
+/// The dimensions of an image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Size {
+    /// The width in pixels.
+    pub width: u32,
+    /// The height in pixels.
+    pub height: u32,
+}
+
+/// A rectangular region of an image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Region {
+    /// The column of the leftmost pixel.
+    pub x: u32,
+    /// The row of the topmost pixel.
+    pub y: u32,
+    /// The width in pixels.
+    pub width: u32,
+    /// The height in pixels.
+    pub height: u32,
+}
+
+/// A summary of the difference between two images, as generated by [`Metadata`].
+///
+/// With the `serde` feature, this is serialized as an object with the fields `color_model`,
+/// `left`, `right` and `size` (each `{"width", "height"}`), `changed_pixels`, and `changed_region`
+/// (`{"x", "y", "width", "height"}`, or `null` if nothing changed).
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ImageMeta {
+    /// The color model of the pixels, such as `RGBA`.
+    pub color_model: String,
+    /// The size of the left image.
+    pub left: Size,
+    /// The size of the right image.
+    pub right: Size,
+    /// The size of the diff, large enough to contain both images.
+    pub size: Size,
+    /// The number of changed pixels, including those outside of one image.
+    pub changed_pixels: u64,
+    /// The smallest region containing every changed pixel.
+    pub changed_region: Option<Region>,
+}
+
+impl<P, C> DiffAlgo<ImageBuffer<P, C>> for Metadata
+where
+    P: Pixel,
+    C: Deref<Target = [P::Subpixel]>,
+{
+    type Diff<'a> = ImageMeta
+    where
+        ImageBuffer<P, C>: 'a;
+
+    fn diff<'a>(l: &'a ImageBuffer<P, C>, r: &'a ImageBuffer<P, C>) -> Self::Diff<'a> {
+        let width = u32::max(l.width(), r.width());
+        let height = u32::max(l.height(), r.height());
+
+        let z = vec![P::Subpixel::zero(); P::CHANNEL_COUNT as usize];
+        let zeroed = P::from_slice(&z);
+
+        let mut changed_pixels = 0;
+        // The first and last changed column and row
+        let mut bounds: Option<(u32, u32, u32, u32)> = None;
+        for h in 0..height {
+            for w in 0..width {
+                let pl = l.get_pixel_checked(w, h).unwrap_or(zeroed);
+                let pr = r.get_pixel_checked(w, h).unwrap_or(zeroed);
+                let transparent = PixelExt::alpha(pl) == Some(&P::Subpixel::zero())
+                    && PixelExt::alpha(pr) == Some(&P::Subpixel::zero());
+                if pl.channels() == pr.channels() || transparent {
+                    continue;
+                }
+                changed_pixels += 1;
+                bounds = Some(match bounds {
+                    Some((x0, y0, x1, y1)) => (x0.min(w), y0.min(h), x1.max(w), y1.max(h)),
+                    None => (w, h, w, h),
+                });
+            }
+        }
+
+        ImageMeta {
+            color_model: P::COLOR_MODEL.to_owned(),
+            left: Size {
+                width: l.width(),
+                height: l.height(),
+            },
+            right: Size {
+                width: r.width(),
+                height: r.height(),
+            },
+            size: Size { width, height },
+            changed_pixels,
+            changed_region: bounds.map(|(x0, y0, x1, y1)| Region {
+                x: x0,
+                y: y0,
+                width: x1 - x0 + 1,
+                height: y1 - y0 + 1,
+            }),
+        }
+    }
+}
+
 impl<P, C> DiffAlgo<ImageBuffer<P, C>> for algo::Default
 where
     P: Pixel,
@@ -376,8 +509,8 @@ impl<P: Pixel, C> Diffable for ImageBuffer<P, C> {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use image::io::Reader;
     use image::ImageFormat;
+    use image::ImageReader;
     use std::fs::File;
     use std::io::BufReader;
     use std::path::{Path, PathBuf};
@@ -393,7 +526,7 @@ mod tests {
 
     fn read_rgba<P: AsRef<Path>>(path: P) -> image::RgbaImage {
         let file = BufReader::new(File::open(path).unwrap());
-        Reader::with_format(file, ImageFormat::Png)
+        ImageReader::with_format(file, ImageFormat::Png)
             .decode()
             .unwrap()
             .into_rgba8()
@@ -440,4 +573,62 @@ mod tests {
         let diff = img1.diff::<RedGreen>(&img2);
         assert_eq!(diff, out);
     }
+
+    #[test]
+    fn test_pixel_patch() {
+        let mut img1 = image::GrayImage::new(1, 2);
+        let mut img2 = image::GrayImage::new(2, 2);
+        img1.put_pixel(0, 0, Luma([200]));
+        img2.put_pixel(0, 0, Luma([10]));
+        img2.put_pixel(1, 1, Luma([255]));
+
+        let diff = img1.diff::<PixelPatch>(&img2);
+        assert_eq!(diff, vec![vec![[-190], [0]], vec![[0], [255]]]);
+    }
+
+    #[test]
+    fn test_metadata() {
+        let mut img1 = image::RgbaImage::new(4, 3);
+        let mut img2 = image::RgbaImage::new(3, 3);
+        img1.put_pixel(1, 1, Rgba([255, 0, 0, 255]));
+        img2.put_pixel(1, 1, Rgba([255, 0, 0, 255]));
+        img2.put_pixel(2, 0, Rgba([0, 0, 255, 255]));
+        img1.put_pixel(3, 2, Rgba([0, 255, 0, 255]));
+
+        let meta = img1.diff::<Metadata>(&img2);
+        assert_eq!(
+            meta.size,
+            Size {
+                width: 4,
+                height: 3
+            }
+        );
+        assert_eq!(meta.changed_pixels, 2);
+        assert_eq!(
+            meta.changed_region,
+            Some(Region {
+                x: 2,
+                y: 0,
+                width: 2,
+                height: 3
+            }),
+        );
+
+        #[cfg(feature = "serde")]
+        {
+            let json = serde_json::to_value(&meta).unwrap();
+            assert_eq!(
+                json,
+                serde_json::json!({
+                    "color_model": "RGBA",
+                    "left": {"width": 4, "height": 3},
+                    "right": {"width": 3, "height": 3},
+                    "size": {"width": 4, "height": 3},
+                    "changed_pixels": 2,
+                    "changed_region": {"x": 2, "y": 0, "width": 2, "height": 3},
+                }),
+            );
+            assert_eq!(serde_json::from_value::<ImageMeta>(json).unwrap(), meta);
+        }
+    }
 }
@@ -0,0 +1,157 @@
+//! Differences of JSON documents, as [`serde_json::Value`]s. Objects are compared by key, and
+//! arrays by their longest common subsequence, with each change located by an RFC 6901 JSON
+//! Pointer. RFC 6902 JSON Patch documents can be generated and applied with [`patch`], and
+//! RFC 7396 JSON Merge Patch documents with [`merge`].
+
+pub mod merge;
+pub mod patch;
+
+use crate::algo::DiffAlgo;
+use crate::builtin::diff_paired;
+use crate::{algo, DiffRes, Diffable};
+use serde_json::Value;
+use std::fmt;
+
+/// Generate a structural diff of two JSON documents. Only changed values are reported.
+pub struct JsonDiff;
+
+/// A single difference between two JSON documents. [`DiffRes::Left`] is a removed value, located
+/// by its pointer in the left document. [`DiffRes::Right`] is an added value, and
+/// [`DiffRes::Both`] a replaced value, both located by their pointer in the right document.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Change<'a> {
+    pub pointer: String,
+    pub res: DiffRes<&'a Value>,
+}
+
+impl fmt::Display for Change<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.res {
+            DiffRes::Left(l) => write!(f, "-{}: {}", self.pointer, l),
+            DiffRes::Both(l, r) => write!(f, " {}: {} -> {}", self.pointer, l, r),
+            DiffRes::Right(r) => write!(f, "+{}: {}", self.pointer, r),
+        }
+    }
+}
+
+/// Append a reference token to a JSON Pointer, escaping it as described by RFC 6901.
+pub fn push_token(pointer: &mut String, token: &str) {
+    pointer.push('/');
+    for c in token.chars() {
+        match c {
+            '~' => pointer.push_str("~0"),
+            '/' => pointer.push_str("~1"),
+            c => pointer.push(c),
+        }
+    }
+}
+
+fn child(pointer: &str, token: &str) -> String {
+    let mut out = pointer.to_owned();
+    push_token(&mut out, token);
+    out
+}
+
+fn diff_array<'a>(pointer: &str, l: &'a [Value], r: &'a [Value], out: &mut Vec<Change<'a>>) {
+    for (idx, res) in diff_paired(l, r) {
+        let pointer = child(pointer, &idx.to_string());
+        match res {
+            DiffRes::Both(lv, rv) => diff_into(&pointer, lv, rv, out),
+            res => out.push(Change { pointer, res }),
+        }
+    }
+}
+
+fn diff_into<'a>(pointer: &str, l: &'a Value, r: &'a Value, out: &mut Vec<Change<'a>>) {
+    match (l, r) {
+        (Value::Object(lm), Value::Object(rm)) => {
+            for (key, lv) in lm {
+                let pointer = child(pointer, key);
+                match rm.get(key) {
+                    Some(rv) => diff_into(&pointer, lv, rv, out),
+                    None => out.push(Change {
+                        pointer,
+                        res: DiffRes::Left(lv),
+                    }),
+                }
+            }
+            for (key, rv) in rm {
+                if !lm.contains_key(key) {
+                    out.push(Change {
+                        pointer: child(pointer, key),
+                        res: DiffRes::Right(rv),
+                    });
+                }
+            }
+        }
+        (Value::Array(la), Value::Array(ra)) => diff_array(pointer, la, ra, out),
+        (l, r) if l != r => out.push(Change {
+            pointer: pointer.to_owned(),
+            res: DiffRes::Both(l, r),
+        }),
+        _ => (),
+    }
+}
+
+impl DiffAlgo<Value> for JsonDiff {
+    type Diff<'a> = Vec<Change<'a>>;
+
+    fn diff<'a>(l: &'a Value, r: &'a Value) -> Self::Diff<'a> {
+        let mut out = Vec::new();
+        diff_into("", l, r, &mut out);
+        out
+    }
+}
+
+impl DiffAlgo<Value> for algo::Default {
+    type Diff<'a> = Vec<Change<'a>>;
+
+    fn diff<'a>(l: &'a Value, r: &'a Value) -> Self::Diff<'a> {
+        JsonDiff::diff(l, r)
+    }
+}
+
+impl Diffable for Value {
+    type Diff<'a, A: DiffAlgo<Self::Item>> = A::Diff<'a>;
+    type Item = Value;
+
+    fn diff<'a, A: DiffAlgo<Self::Item>>(&'a self, other: &'a Self) -> A::Diff<'a> {
+        A::diff(self, other)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_json() {
+        let a = json!({
+            "name": "semdiff",
+            "a/b": 1,
+            "tags": ["diff", "json", "text"],
+            "servers": [{"port": 80}, {"port": 81}],
+        });
+        let b = json!({
+            "name": "semdiff",
+            "a/b": 2,
+            "tags": ["diff", "text", "yaml"],
+            "servers": [{"port": 80}, {"port": 8080}],
+            "new": null,
+        });
+        let d = crate::diff(&a, &b);
+
+        let lines = d.iter().map(Change::to_string).collect::<Vec<_>>();
+        assert_eq!(
+            lines,
+            vec![
+                " /a~1b: 1 -> 2",
+                " /servers/1/port: 81 -> 8080",
+                "-/tags/1: \"json\"",
+                "+/tags/2: \"yaml\"",
+                "+/new: null",
+            ],
+        );
+    }
+}
@@ -0,0 +1,153 @@
+//! RFC 7396 JSON Merge Patch documents. A merge patch mirrors the shape of the document it
+//! modifies: object members are merged recursively, `null` removes a member, and any other value,
+//! including an array, replaces the target outright.
+
+use crate::algo::{DiffAlgo, DiffPatch};
+use serde_json::{Map, Value};
+
+/// Generate a JSON Merge Patch that transforms the left document into the right document.
+///
+/// Merge patches can't express setting a member to `null`, as that removes it instead. Such members
+/// of the right document are omitted, so applying the patch leaves them absent.
+pub struct MergePatch;
+
+fn diff_value(l: &Value, r: &Value) -> Option<Value> {
+    match (l, r) {
+        (Value::Object(lm), Value::Object(rm)) => {
+            let mut out = Map::new();
+            for key in lm.keys() {
+                if !rm.contains_key(key) {
+                    out.insert(key.clone(), Value::Null);
+                }
+            }
+            for (key, rv) in rm {
+                let patch = match lm.get(key) {
+                    Some(lv) => diff_value(lv, rv),
+                    None => Some(strip_nulls(rv)),
+                };
+                if let Some(patch) = patch {
+                    out.insert(key.clone(), patch);
+                }
+            }
+            (!out.is_empty()).then_some(Value::Object(out))
+        }
+        (l, r) if l == r => None,
+        (_, r) => Some(strip_nulls(r)),
+    }
+}
+
+/// Remove `null` members of objects, which a merge patch would otherwise treat as deletions.
+fn strip_nulls(val: &Value) -> Value {
+    match val {
+        Value::Object(obj) => Value::Object(
+            obj.iter()
+                .filter(|(_, v)| !v.is_null())
+                .map(|(k, v)| (k.clone(), strip_nulls(v)))
+                .collect(),
+        ),
+        val => val.clone(),
+    }
+}
+
+/// Apply a merge patch to a document.
+pub fn apply(doc: &mut Value, patch: &Value) {
+    let Value::Object(patch) = patch else {
+        *doc = patch.clone();
+        return;
+    };
+    if !doc.is_object() {
+        *doc = Value::Object(Map::new());
+    }
+    let Value::Object(obj) = doc else {
+        unreachable!()
+    };
+    for (key, val) in patch {
+        if val.is_null() {
+            obj.remove(key);
+        } else {
+            apply(obj.entry(key.clone()).or_insert(Value::Null), val);
+        }
+    }
+}
+
+impl DiffAlgo<Value> for MergePatch {
+    type Diff<'a> = Value;
+
+    fn diff<'a>(l: &'a Value, r: &'a Value) -> Self::Diff<'a> {
+        match diff_value(l, r) {
+            Some(patch) => patch,
+            // Only an empty object leaves an object unchanged, while any other document is replaced
+            None if r.is_object() => Value::Object(Map::new()),
+            None => r.clone(),
+        }
+    }
+}
+
+/// Merge patches are lossless apart from `null` members of the right document, as described on
+/// [`MergePatch`].
+impl DiffPatch<Value> for MergePatch {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Diffable;
+    use serde_json::json;
+
+    #[test]
+    fn test_merge_patch() {
+        let a = json!({
+            "title": "Goodbye!",
+            "author": {"givenName": "John", "familyName": "Doe"},
+            "tags": ["example", "sample"],
+            "content": "This will be unchanged",
+        });
+        let b = json!({
+            "title": "Hello!",
+            "author": {"givenName": "John"},
+            "tags": ["example"],
+            "content": "This will be unchanged",
+            "phoneNumber": "+01-123-456-7890",
+        });
+        let patch = a.diff::<MergePatch>(&b);
+
+        assert_eq!(
+            patch,
+            json!({
+                "title": "Hello!",
+                "author": {"familyName": null},
+                "tags": ["example"],
+                "phoneNumber": "+01-123-456-7890",
+            }),
+        );
+
+        let mut patched = a.clone();
+        apply(&mut patched, &patch);
+        assert_eq!(patched, b);
+
+        assert_eq!(a.diff::<MergePatch>(&a), json!({}));
+
+        // A patch of equal documents must leave them unchanged, which only objects can merge into
+        for doc in [json!([1]), json!("a"), json!(null)] {
+            let patch = doc.diff::<MergePatch>(&doc);
+            let mut patched = doc.clone();
+            apply(&mut patched, &patch);
+            assert_eq!(patched, doc);
+        }
+    }
+
+    #[test]
+    fn test_merge_apply() {
+        let mut doc = json!({"a": "b", "c": [1, 2]});
+        apply(&mut doc, &json!({"a": {"d": null, "e": 1}, "c": null}));
+        assert_eq!(doc, json!({"a": {"e": 1}}));
+
+        apply(&mut doc, &json!(["x"]));
+        assert_eq!(doc, json!(["x"]));
+
+        let a = json!({"a": 1});
+        let b = json!({"a": {"b": null, "c": 2}});
+        let mut patched = a.clone();
+        apply(&mut patched, &a.diff::<MergePatch>(&b));
+        assert_eq!(patched, json!({"a": {"c": 2}}));
+    }
+}
@@ -0,0 +1,560 @@
+//! RFC 6902 JSON Patch documents. [`JsonPatch`] generates a list of [`Operation`]s that transform
+//! one JSON document into another, which can be converted to and from standard JSON Patch
+//! documents, and applied to a [`Value`].
+
+use super::child;
+use crate::algo::{DiffAlgo, DiffPatch};
+use crate::builtin::pair_lcs;
+use crate::DiffRes;
+use serde_json::{Map, Value};
+use std::fmt;
+
+/// Generate a JSON Patch that transforms the left document into the right document.
+pub struct JsonPatch;
+
+/// A single JSON Patch operation. Paths are JSON Pointers, as described by RFC 6901.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Operation {
+    Add { path: String, value: Value },
+    Remove { path: String },
+    Replace { path: String, value: Value },
+    Move { from: String, path: String },
+    Copy { from: String, path: String },
+    Test { path: String, value: Value },
+}
+
+impl Operation {
+    /// The name of this operation, as used in the `op` member of a JSON Patch document.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Operation::Add { .. } => "add",
+            Operation::Remove { .. } => "remove",
+            Operation::Replace { .. } => "replace",
+            Operation::Move { .. } => "move",
+            Operation::Copy { .. } => "copy",
+            Operation::Test { .. } => "test",
+        }
+    }
+
+    /// The location this operation targets.
+    pub fn path(&self) -> &str {
+        match self {
+            Operation::Add { path, .. }
+            | Operation::Remove { path }
+            | Operation::Replace { path, .. }
+            | Operation::Move { path, .. }
+            | Operation::Copy { path, .. }
+            | Operation::Test { path, .. } => path,
+        }
+    }
+
+    /// Convert this operation to its JSON Patch representation.
+    pub fn to_value(&self) -> Value {
+        let mut obj = Map::new();
+        obj.insert(String::from("op"), Value::from(self.name()));
+        if let Operation::Move { from, .. } | Operation::Copy { from, .. } = self {
+            obj.insert(String::from("from"), Value::from(from.as_str()));
+        }
+        obj.insert(String::from("path"), Value::from(self.path()));
+        if let Operation::Add { value, .. }
+        | Operation::Replace { value, .. }
+        | Operation::Test { value, .. } = self
+        {
+            obj.insert(String::from("value"), value.clone());
+        }
+        Value::Object(obj)
+    }
+
+    /// Parse a single operation from its JSON Patch representation.
+    pub fn from_value(value: &Value) -> Result<Operation, ParseError> {
+        let obj = value.as_object().ok_or(ParseError::NotObject)?;
+        let string = |name: &'static str| -> Result<String, ParseError> {
+            let val = obj.get(name).ok_or(ParseError::MissingMember(name))?;
+            let val = val.as_str().ok_or(ParseError::InvalidMember(name))?;
+            parse_pointer(val).map_err(|_| ParseError::InvalidMember(name))?;
+            Ok(val.to_owned())
+        };
+        let value = || -> Result<Value, ParseError> {
+            obj.get("value")
+                .cloned()
+                .ok_or(ParseError::MissingMember("value"))
+        };
+
+        let op = obj.get("op").ok_or(ParseError::MissingMember("op"))?;
+        let op = op.as_str().ok_or(ParseError::InvalidMember("op"))?;
+        Ok(match op {
+            "add" => Operation::Add {
+                path: string("path")?,
+                value: value()?,
+            },
+            "remove" => Operation::Remove {
+                path: string("path")?,
+            },
+            "replace" => Operation::Replace {
+                path: string("path")?,
+                value: value()?,
+            },
+            "move" => Operation::Move {
+                from: string("from")?,
+                path: string("path")?,
+            },
+            "copy" => Operation::Copy {
+                from: string("from")?,
+                path: string("path")?,
+            },
+            "test" => Operation::Test {
+                path: string("path")?,
+                value: value()?,
+            },
+            op => return Err(ParseError::UnknownOp(op.to_owned())),
+        })
+    }
+}
+
+/// Convert a list of operations into a JSON Patch document.
+pub fn to_value(ops: &[Operation]) -> Value {
+    Value::Array(ops.iter().map(Operation::to_value).collect())
+}
+
+/// Parse a JSON Patch document into a list of operations.
+pub fn from_value(doc: &Value) -> Result<Vec<Operation>, Error<ParseError>> {
+    let ops = doc.as_array().ok_or(Error {
+        index: None,
+        kind: ParseError::NotArray,
+    })?;
+    ops.iter()
+        .enumerate()
+        .map(|(idx, op)| {
+            Operation::from_value(op).map_err(|kind| Error {
+                index: Some(idx),
+                kind,
+            })
+        })
+        .collect()
+}
+
+/// An error from handling a JSON Patch, alongside the index of the operation that caused it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Error<K> {
+    /// The index of the failing operation, if the error is specific to one.
+    pub index: Option<usize>,
+    pub kind: K,
+}
+
+impl<K: fmt::Display> fmt::Display for Error<K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.index {
+            Some(idx) => write!(f, "operation {}: {}", idx, self.kind),
+            None => write!(f, "{}", self.kind),
+        }
+    }
+}
+
+impl<K: fmt::Debug + fmt::Display> std::error::Error for Error<K> {}
+
+/// The reason a JSON Patch document failed to parse.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseError {
+    /// The document is not an array of operations.
+    NotArray,
+    /// An operation is not an object.
+    NotObject,
+    /// An operation is missing a required member.
+    MissingMember(&'static str),
+    /// A member of an operation has the wrong type, or is not a valid JSON Pointer.
+    InvalidMember(&'static str),
+    /// The `op` member is not a known operation.
+    UnknownOp(String),
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::NotArray => write!(f, "patch document is not an array"),
+            ParseError::NotObject => write!(f, "operation is not an object"),
+            ParseError::MissingMember(name) => write!(f, "missing member `{}`", name),
+            ParseError::InvalidMember(name) => write!(f, "invalid member `{}`", name),
+            ParseError::UnknownOp(op) => write!(f, "unknown operation `{}`", op),
+        }
+    }
+}
+
+/// The reason a JSON Patch failed to apply.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ApplyError {
+    /// A path is not a valid JSON Pointer.
+    InvalidPointer(String),
+    /// A path does not refer to an existing value.
+    NotFound(String),
+    /// A path uses an array index which is malformed or out of bounds.
+    InvalidIndex(String),
+    /// A value can't be moved into one of its own children.
+    MoveIntoChild { from: String, path: String },
+    /// A `test` operation found a different value than expected.
+    TestFailed {
+        path: String,
+        expected: Value,
+        actual: Value,
+    },
+}
+
+impl fmt::Display for ApplyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ApplyError::InvalidPointer(path) => write!(f, "invalid JSON Pointer `{}`", path),
+            ApplyError::NotFound(path) => write!(f, "no value at `{}`", path),
+            ApplyError::InvalidIndex(path) => write!(f, "invalid array index in `{}`", path),
+            ApplyError::MoveIntoChild { from, path } => {
+                write!(f, "cannot move `{}` into its child `{}`", from, path)
+            }
+            ApplyError::TestFailed {
+                path,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "test failed at `{}`: expected {}, found {}",
+                path, expected, actual
+            ),
+        }
+    }
+}
+
+/// Split a JSON Pointer into its unescaped reference tokens.
+fn parse_pointer(pointer: &str) -> Result<Vec<String>, ApplyError> {
+    if pointer.is_empty() {
+        return Ok(Vec::new());
+    }
+    let invalid = || ApplyError::InvalidPointer(pointer.to_owned());
+    let rest = pointer.strip_prefix('/').ok_or_else(invalid)?;
+    rest.split('/')
+        .map(|token| {
+            let mut out = String::with_capacity(token.len());
+            let mut chars = token.chars();
+            while let Some(c) = chars.next() {
+                match c {
+                    '~' => match chars.next() {
+                        Some('0') => out.push('~'),
+                        Some('1') => out.push('/'),
+                        _ => return Err(invalid()),
+                    },
+                    c => out.push(c),
+                }
+            }
+            Ok(out)
+        })
+        .collect()
+}
+
+fn parse_index(token: &str, len: usize, path: &str) -> Result<usize, ApplyError> {
+    let invalid = || ApplyError::InvalidIndex(path.to_owned());
+    if token.is_empty() || (token.len() > 1 && token.starts_with('0')) {
+        return Err(invalid());
+    }
+    if !token.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(invalid());
+    }
+    let idx = token.parse::<usize>().map_err(|_| invalid())?;
+    if idx < len {
+        Ok(idx)
+    } else {
+        Err(invalid())
+    }
+}
+
+fn get_mut<'a>(
+    doc: &'a mut Value,
+    tokens: &[String],
+    path: &str,
+) -> Result<&'a mut Value, ApplyError> {
+    tokens.iter().try_fold(doc, |cur, token| match cur {
+        Value::Object(obj) => obj
+            .get_mut(token)
+            .ok_or_else(|| ApplyError::NotFound(path.to_owned())),
+        Value::Array(arr) => {
+            let idx = parse_index(token, arr.len(), path)?;
+            Ok(&mut arr[idx])
+        }
+        _ => Err(ApplyError::NotFound(path.to_owned())),
+    })
+}
+
+fn add(doc: &mut Value, path: &str, value: Value) -> Result<(), ApplyError> {
+    let tokens = parse_pointer(path)?;
+    let Some((last, parent)) = tokens.split_last() else {
+        *doc = value;
+        return Ok(());
+    };
+    match get_mut(doc, parent, path)? {
+        Value::Object(obj) => {
+            obj.insert(last.clone(), value);
+        }
+        Value::Array(arr) if last == "-" => arr.push(value),
+        Value::Array(arr) => {
+            let idx = parse_index(last, arr.len() + 1, path)?;
+            arr.insert(idx, value);
+        }
+        _ => return Err(ApplyError::NotFound(path.to_owned())),
+    }
+    Ok(())
+}
+
+fn remove(doc: &mut Value, path: &str) -> Result<Value, ApplyError> {
+    let tokens = parse_pointer(path)?;
+    let (last, parent) = tokens
+        .split_last()
+        .ok_or_else(|| ApplyError::NotFound(path.to_owned()))?;
+    match get_mut(doc, parent, path)? {
+        Value::Object(obj) => obj
+            .remove(last)
+            .ok_or_else(|| ApplyError::NotFound(path.to_owned())),
+        Value::Array(arr) => {
+            let idx = parse_index(last, arr.len(), path)?;
+            Ok(arr.remove(idx))
+        }
+        _ => Err(ApplyError::NotFound(path.to_owned())),
+    }
+}
+
+fn apply_op(doc: &mut Value, op: &Operation) -> Result<(), ApplyError> {
+    match op {
+        Operation::Add { path, value } => add(doc, path, value.clone()),
+        Operation::Remove { path } => remove(doc, path).map(|_| ()),
+        Operation::Replace { path, value } => {
+            let tokens = parse_pointer(path)?;
+            *get_mut(doc, &tokens, path)? = value.clone();
+            Ok(())
+        }
+        Operation::Move { from, path } => {
+            if path.starts_with(from.as_str()) && path[from.len()..].starts_with('/') {
+                return Err(ApplyError::MoveIntoChild {
+                    from: from.clone(),
+                    path: path.clone(),
+                });
+            }
+            let value = remove(doc, from)?;
+            add(doc, path, value)
+        }
+        Operation::Copy { from, path } => {
+            let tokens = parse_pointer(from)?;
+            let value = get_mut(doc, &tokens, from)?.clone();
+            add(doc, path, value)
+        }
+        Operation::Test { path, value } => {
+            let tokens = parse_pointer(path)?;
+            let actual = get_mut(doc, &tokens, path)?;
+            if actual == value {
+                Ok(())
+            } else {
+                Err(ApplyError::TestFailed {
+                    path: path.clone(),
+                    expected: value.clone(),
+                    actual: actual.clone(),
+                })
+            }
+        }
+    }
+}
+
+/// Apply a list of operations to a document. Application is atomic: if any operation fails, the
+/// document is left unchanged and the error reports which operation failed.
+pub fn apply(doc: &mut Value, ops: &[Operation]) -> Result<(), Error<ApplyError>> {
+    let mut out = doc.clone();
+    for (idx, op) in ops.iter().enumerate() {
+        apply_op(&mut out, op).map_err(|kind| Error {
+            index: Some(idx),
+            kind,
+        })?;
+    }
+    *doc = out;
+    Ok(())
+}
+
+fn diff_array(pointer: &str, l: &[Value], r: &[Value], out: &mut Vec<Operation>) {
+    // Operations are applied in order, so elements are located by their index in the partially
+    // patched array, which matches the right side up to that point
+    for p in pair_lcs(l, r, false) {
+        let path = child(pointer, &p.right.to_string());
+        match p.res {
+            DiffRes::Left(_) => out.push(Operation::Remove { path }),
+            DiffRes::Both(lv, rv) => diff_into(&path, lv, rv, out),
+            DiffRes::Right(rv) => out.push(Operation::Add {
+                path,
+                value: rv.clone(),
+            }),
+        }
+    }
+}
+
+fn diff_into(pointer: &str, l: &Value, r: &Value, out: &mut Vec<Operation>) {
+    match (l, r) {
+        (Value::Object(lm), Value::Object(rm)) => {
+            let mut removed = lm
+                .iter()
+                .filter(|(k, _)| !rm.contains_key(*k))
+                .collect::<Vec<_>>();
+            let mut added = Vec::new();
+            for (key, rv) in rm {
+                if lm.contains_key(key) {
+                    continue;
+                }
+                // A key that was renamed without changing its value becomes a move
+                match removed.iter().position(|(_, lv)| *lv == rv) {
+                    Some(pos) => {
+                        let (from, _) = removed.remove(pos);
+                        out.push(Operation::Move {
+                            from: child(pointer, from),
+                            path: child(pointer, key),
+                        });
+                    }
+                    None => added.push((key, rv)),
+                }
+            }
+            for (key, _) in removed {
+                out.push(Operation::Remove {
+                    path: child(pointer, key),
+                });
+            }
+            for (key, lv) in lm {
+                if let Some(rv) = rm.get(key) {
+                    diff_into(&child(pointer, key), lv, rv, out);
+                }
+            }
+            for (key, rv) in added {
+                out.push(Operation::Add {
+                    path: child(pointer, key),
+                    value: rv.clone(),
+                });
+            }
+        }
+        (Value::Array(la), Value::Array(ra)) => diff_array(pointer, la, ra, out),
+        (l, r) if l != r => out.push(Operation::Replace {
+            path: pointer.to_owned(),
+            value: r.clone(),
+        }),
+        _ => (),
+    }
+}
+
+impl DiffAlgo<Value> for JsonPatch {
+    type Diff<'a> = Vec<Operation>;
+
+    fn diff<'a>(l: &'a Value, r: &'a Value) -> Self::Diff<'a> {
+        let mut out = Vec::new();
+        diff_into("", l, r, &mut out);
+        out
+    }
+}
+
+impl DiffPatch<Value> for JsonPatch {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Diffable;
+    use serde_json::json;
+
+    #[test]
+    fn test_patch_roundtrip() {
+        let a = json!({
+            "name": "semdiff",
+            "old": {"x": 1},
+            "tags": ["diff", "json", "text"],
+            "servers": [{"port": 80}, {"port": 81}],
+            "gone": true,
+        });
+        let b = json!({
+            "name": "semdiff",
+            "new": {"x": 1},
+            "tags": ["diff", "text", "yaml"],
+            "servers": [{"port": 80}, {"port": 8080}],
+            "added": [1],
+        });
+        let ops = a.diff::<JsonPatch>(&b);
+
+        assert_eq!(
+            to_value(&ops),
+            json!([
+                {"op": "move", "from": "/old", "path": "/new"},
+                {"op": "remove", "path": "/gone"},
+                {"op": "replace", "path": "/servers/1/port", "value": 8080},
+                {"op": "remove", "path": "/tags/1"},
+                {"op": "add", "path": "/tags/2", "value": "yaml"},
+                {"op": "add", "path": "/added", "value": [1]},
+            ]),
+        );
+
+        let mut patched = a.clone();
+        apply(&mut patched, &from_value(&to_value(&ops)).unwrap()).unwrap();
+        assert_eq!(patched, b);
+    }
+
+    #[test]
+    fn test_patch_apply() {
+        let mut doc = json!({"a/b": [1, 2], "c": {"d": "e"}});
+        let ops = from_value(&json!([
+            {"op": "test", "path": "/a~1b/0", "value": 1},
+            {"op": "add", "path": "/a~1b/-", "value": 3},
+            {"op": "copy", "from": "/c", "path": "/f"},
+            {"op": "replace", "path": "/f/d", "value": "g"},
+        ]))
+        .unwrap();
+        apply(&mut doc, &ops).unwrap();
+        assert_eq!(
+            doc,
+            json!({"a/b": [1, 2, 3], "c": {"d": "e"}, "f": {"d": "g"}})
+        );
+    }
+
+    #[test]
+    fn test_patch_errors() {
+        let mut doc = json!({"a": [1, 2]});
+        let ops = from_value(&json!([
+            {"op": "add", "path": "/b", "value": 1},
+            {"op": "test", "path": "/a/1", "value": 3},
+        ]))
+        .unwrap();
+        assert_eq!(
+            apply(&mut doc, &ops),
+            Err(Error {
+                index: Some(1),
+                kind: ApplyError::TestFailed {
+                    path: String::from("/a/1"),
+                    expected: json!(3),
+                    actual: json!(2),
+                },
+            }),
+        );
+        assert_eq!(doc, json!({"a": [1, 2]}));
+
+        let ops = [Operation::Remove {
+            path: String::from("/a/01"),
+        }];
+        assert_eq!(
+            apply(&mut doc, &ops).unwrap_err().kind,
+            ApplyError::InvalidIndex(String::from("/a/01")),
+        );
+        let ops = [Operation::Move {
+            from: String::from("/a"),
+            path: String::from("/a/0"),
+        }];
+        assert!(matches!(
+            apply(&mut doc, &ops).unwrap_err().kind,
+            ApplyError::MoveIntoChild { .. },
+        ));
+
+        assert_eq!(
+            from_value(&json!([{"op": "add", "path": "/a"}])),
+            Err(Error {
+                index: Some(0),
+                kind: ParseError::MissingMember("value"),
+            }),
+        );
+        assert_eq!(
+            from_value(&json!([{"op": "frob", "path": "/a"}]))
+                .unwrap_err()
+                .kind,
+            ParseError::UnknownOp(String::from("frob")),
+        );
+    }
+}
@@ -3,20 +3,65 @@
 //!
 
 pub mod algo;
+pub mod block;
 pub mod builtin;
+#[cfg(feature = "csv")]
+pub mod csv;
+pub mod float;
 pub mod fmt;
 #[cfg(feature = "img")]
 pub mod img;
+#[cfg(feature = "json")]
+pub mod json;
+#[cfg(feature = "num")]
+pub mod num;
+pub mod path;
+#[cfg(feature = "syn")]
+pub mod rust;
+#[cfg(feature = "tree-sitter")]
+pub mod syntax;
+#[cfg(feature = "toml")]
+pub mod toml;
+pub mod tree;
+#[cfg(feature = "serde")]
+pub mod value;
+#[cfg(feature = "xml")]
+pub mod xml;
+#[cfg(feature = "yaml")]
+pub mod yaml;
 
 use algo::DiffAlgo;
 
-#[derive(Debug, PartialEq, Eq, Hash, PartialOrd)]
+/// Derive [`Diffable`] for a struct or enum, diffing each field with its own implementation.
+#[cfg(feature = "derive")]
+pub use semdiff_derive::Diffable;
+
+/// A value present in only the left input, in both inputs, or in only the right input.
+///
+/// With the `serde` feature, this is serialized as an object with a single key naming the variant,
+/// `{"left": l}`, `{"both": [l, r]}` or `{"right": r}`. Borrowed diffs can be converted to an owned
+/// form for deserialization with [`DiffRes::into_owned`], such as `Vec<DiffRes<Vec<T>>>` for the
+/// diff of a sequence, `Vec<DiffRes<String>>` for lines of text, or `Vec<DiffRes<T>>` for a set.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
 pub enum DiffRes<T> {
     Left(T),
     Both(T, T),
     Right(T),
 }
 
+impl<T: ?Sized + ToOwned> DiffRes<&T> {
+    /// Clone the borrowed values of this result, so it no longer borrows from the inputs.
+    pub fn into_owned(self) -> DiffRes<T::Owned> {
+        match self {
+            DiffRes::Left(l) => DiffRes::Left(l.to_owned()),
+            DiffRes::Both(l, r) => DiffRes::Both(l.to_owned(), r.to_owned()),
+            DiffRes::Right(r) => DiffRes::Right(r.to_owned()),
+        }
+    }
+}
+
 /// A type that supports determining the difference between two instances. A difference should
 /// generally be meaningful to a person, but may not allow re-creating the original values from
 /// one-another. Difference algorithms that allow that are known as 'patch' differences.
@@ -36,7 +81,7 @@ pub trait Diffable {
 }
 
 /// Generate the difference between two types, using the default difference algorithm.
-pub fn diff<'a, T: Diffable>(a: &'a T, b: &'a T) -> T::Diff<'a, algo::Default>
+pub fn diff<'a, T: Diffable + ?Sized>(a: &'a T, b: &'a T) -> T::Diff<'a, algo::Default>
 where
     algo::Default: DiffAlgo<T::Item>,
 {
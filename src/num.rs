@@ -0,0 +1,276 @@
+//! Element-wise differences of numeric arrays, for comparing scientific outputs. Rather than only
+//! reporting which elements changed, this computes the delta of every element, and summary
+//! statistics of the error with the locations of the worst offenders. Slices are supported, and
+//! [`ndarray`] arrays of any dimension with the `ndarray` feature.
+
+use crate::algo::DiffAlgo;
+use num_traits::{Num, ToPrimitive};
+use std::fmt;
+
+/// Generate the element-wise deltas of two numeric arrays of the same shape, and statistics about
+/// them. The `WORST` largest changes are reported in [`Stats::worst`].
+pub struct NumericDiff<const WORST: usize = 5>;
+
+/// An error from diffing two arrays with different shapes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShapeMismatch {
+    pub left: Vec<usize>,
+    pub right: Vec<usize>,
+}
+
+impl fmt::Display for ShapeMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "cannot diff arrays of shape {:?} and {:?}",
+            self.left, self.right
+        )
+    }
+}
+
+impl std::error::Error for ShapeMismatch {}
+
+/// A single changed element.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Delta<I> {
+    pub index: I,
+    pub left: f64,
+    pub right: f64,
+    /// `right - left`.
+    pub delta: f64,
+}
+
+/// Summary statistics of the deltas between two arrays. Deltas which are NaN, because only one
+/// side is NaN, are counted in `nan` and not included in the errors.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Stats<I> {
+    /// The number of elements compared.
+    pub count: usize,
+    /// The number of elements with a non-zero delta, including NaN.
+    pub changed: usize,
+    pub nan: usize,
+    pub max_abs_error: f64,
+    pub mean_abs_error: f64,
+    /// The root mean square of the deltas.
+    pub rms: f64,
+    /// The largest changes, largest first. NaN deltas come before all others.
+    pub worst: Vec<Delta<I>>,
+}
+
+/// The result of a [`NumericDiff`]. `deltas` has the same shape as the inputs.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NumDiff<I, D> {
+    pub deltas: D,
+    pub stats: Stats<I>,
+}
+
+fn to_f64<T: ToPrimitive>(val: &T) -> f64 {
+    val.to_f64().unwrap_or(f64::NAN)
+}
+
+/// The delta between two values. NaN is considered equal to NaN.
+fn delta(l: f64, r: f64) -> f64 {
+    if l == r || (l.is_nan() && r.is_nan()) {
+        0.0
+    } else {
+        r - l
+    }
+}
+
+fn stats<I>(worst: usize, items: impl Iterator<Item = (I, f64, f64, f64)>) -> Stats<I> {
+    let mut count = 0;
+    let mut nan = 0;
+    let (mut max, mut sum, mut sum_sq) = (0.0, 0.0, 0.0);
+    let mut changed = Vec::new();
+    for (index, left, right, delta) in items {
+        count += 1;
+        if delta == 0.0 {
+            continue;
+        }
+        if delta.is_nan() {
+            nan += 1;
+        } else {
+            max = f64::max(max, delta.abs());
+            sum += delta.abs();
+            sum_sq += delta * delta;
+        }
+        changed.push(Delta {
+            index,
+            left,
+            right,
+            delta,
+        });
+    }
+
+    let valid = (count - nan).max(1) as f64;
+    let num_changed = changed.len();
+    changed.sort_by(|l, r| r.delta.abs().total_cmp(&l.delta.abs()));
+    changed.truncate(worst);
+    Stats {
+        count,
+        changed: num_changed,
+        nan,
+        max_abs_error: max,
+        mean_abs_error: sum / valid,
+        rms: (sum_sq / valid).sqrt(),
+        worst: changed,
+    }
+}
+
+impl<T: Num + ToPrimitive, const WORST: usize> DiffAlgo<[T]> for NumericDiff<WORST> {
+    type Diff<'a> = Result<NumDiff<usize, Vec<f64>>, ShapeMismatch>
+    where
+        T: 'a;
+
+    fn diff<'a>(l: &'a [T], r: &'a [T]) -> Self::Diff<'a> {
+        if l.len() != r.len() {
+            return Err(ShapeMismatch {
+                left: vec![l.len()],
+                right: vec![r.len()],
+            });
+        }
+        let values = l
+            .iter()
+            .zip(r)
+            .map(|(l, r)| (to_f64(l), to_f64(r)))
+            .collect::<Vec<_>>();
+        let deltas = values.iter().map(|&(l, r)| delta(l, r)).collect::<Vec<_>>();
+        let stats = stats(
+            WORST,
+            values
+                .iter()
+                .zip(&deltas)
+                .enumerate()
+                .map(|(idx, (&(l, r), &d))| (idx, l, r, d)),
+        );
+        Ok(NumDiff { deltas, stats })
+    }
+}
+
+#[cfg(feature = "ndarray")]
+mod array {
+    use super::*;
+    use crate::{algo, Diffable};
+    use ndarray::{Array, ArrayBase, Data, Dimension, Zip};
+
+    impl<S, D, const WORST: usize> DiffAlgo<ArrayBase<S, D>> for NumericDiff<WORST>
+    where
+        S: Data,
+        S::Elem: Num + ToPrimitive,
+        D: Dimension,
+    {
+        type Diff<'a> = Result<NumDiff<D::Pattern, Array<f64, D>>, ShapeMismatch>
+        where
+            ArrayBase<S, D>: 'a;
+
+        fn diff<'a>(l: &'a ArrayBase<S, D>, r: &'a ArrayBase<S, D>) -> Self::Diff<'a> {
+            if l.shape() != r.shape() {
+                return Err(ShapeMismatch {
+                    left: l.shape().to_vec(),
+                    right: r.shape().to_vec(),
+                });
+            }
+            let deltas = Zip::from(l)
+                .and(r)
+                .map_collect(|l, r| delta(to_f64(l), to_f64(r)));
+            let stats = stats(
+                WORST,
+                l.indexed_iter()
+                    .zip(r)
+                    .zip(&deltas)
+                    .map(|(((idx, l), r), &d)| (idx, to_f64(l), to_f64(r), d)),
+            );
+            Ok(NumDiff { deltas, stats })
+        }
+    }
+
+    impl<S, D> DiffAlgo<ArrayBase<S, D>> for algo::Default
+    where
+        S: Data,
+        S::Elem: Num + ToPrimitive,
+        D: Dimension,
+    {
+        type Diff<'a> = <NumericDiff as DiffAlgo<ArrayBase<S, D>>>::Diff<'a>
+        where
+            ArrayBase<S, D>: 'a;
+
+        fn diff<'a>(l: &'a ArrayBase<S, D>, r: &'a ArrayBase<S, D>) -> Self::Diff<'a> {
+            <NumericDiff>::diff(l, r)
+        }
+    }
+
+    impl<S: Data, D: Dimension> Diffable for ArrayBase<S, D> {
+        type Diff<'a, A: DiffAlgo<Self::Item>> = A::Diff<'a>
+        where
+            Self: 'a;
+        type Item = Self;
+
+        fn diff<'a, A: DiffAlgo<Self::Item>>(&'a self, other: &'a Self) -> A::Diff<'a> {
+            A::diff(self, other)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Diffable;
+
+    #[test]
+    fn test_numeric() {
+        let a = [1.0, 2.0, 3.0, f64::NAN, 5.0];
+        let b = [1.0, 2.5, 1.0, f64::NAN, 5.5];
+        let d = a.diff::<NumericDiff<2>>(&b).unwrap();
+
+        assert_eq!(d.deltas, vec![0.0, 0.5, -2.0, 0.0, 0.5]);
+        assert_eq!(d.stats.count, 5);
+        assert_eq!(d.stats.changed, 3);
+        assert_eq!(d.stats.max_abs_error, 2.0);
+        assert_eq!(d.stats.mean_abs_error, 3.0 / 5.0);
+        assert_eq!(d.stats.rms, (4.5f64 / 5.0).sqrt());
+        assert_eq!(
+            d.stats.worst,
+            vec![
+                Delta {
+                    index: 2,
+                    left: 3.0,
+                    right: 1.0,
+                    delta: -2.0
+                },
+                Delta {
+                    index: 1,
+                    left: 2.0,
+                    right: 2.5,
+                    delta: 0.5
+                },
+            ],
+        );
+
+        let mut out = String::new();
+        crate::fmt::fmt_stats(&d.stats, &mut out).unwrap();
+        assert_eq!(
+            out,
+            "compared 5 elements, 3 changed, 0 NaN\n\
+             max abs error:  2\n\
+             mean abs error: 0.6\n\
+             rms:            0.9486832980505138\n\
+             worst:\n  \
+             at 2: 3 -> 1 (-2)\n  \
+             at 1: 2 -> 2.5 (+0.5)\n",
+        );
+
+        assert!([1, 2][..].diff::<NumericDiff>(&[1, 2, 3][..]).is_err());
+    }
+
+    #[cfg(feature = "ndarray")]
+    #[test]
+    fn test_numeric_array() {
+        let a = ndarray::array![[1, 2], [3, 4]];
+        let b = ndarray::array![[1, 2], [3, 7]];
+        let d = crate::diff(&a, &b).unwrap();
+
+        assert_eq!(d.deltas, ndarray::array![[0.0, 0.0], [0.0, 3.0]]);
+        assert_eq!(d.stats.worst[0].index, (1, 1));
+        assert!(crate::diff(&a, &ndarray::array![[1, 2]]).is_err());
+    }
+}
@@ -0,0 +1,368 @@
+//! Locations of changes within nested diffs. A [`DiffPath`] addresses a value by the fields,
+//! indices and map keys leading to it, and [`Flatten`] walks a nested diff to list every change
+//! alongside its path, so changes can be filtered, grouped and reported by location.
+
+use crate::builtin::{KeyedEntry, MapEntry, ValueDiff, VariantDiff};
+use crate::DiffRes;
+use std::collections::{HashMap, HashSet};
+use std::fmt::{self, Debug};
+
+/// One step of a [`DiffPath`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Segment {
+    /// A field of a struct.
+    Field(&'static str),
+    /// An element of a sequence, or a field of a tuple.
+    Index(usize),
+    /// An entry of a map or set, with the key rendered as a string.
+    Key(String),
+}
+
+/// The location of a value, displayed like `.config.servers[2].port`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct DiffPath(pub Vec<Segment>);
+
+impl DiffPath {
+    /// This path, extended by one more segment.
+    pub fn with(&self, seg: Segment) -> DiffPath {
+        let mut out = self.clone();
+        out.0.push(seg);
+        out
+    }
+
+    /// Whether this path is `prefix`, or a location within it.
+    pub fn starts_with(&self, prefix: &DiffPath) -> bool {
+        self.0.starts_with(&prefix.0)
+    }
+
+    /// Render this path as an RFC 6901 JSON Pointer.
+    pub fn to_pointer(&self) -> String {
+        let mut out = String::new();
+        for seg in &self.0 {
+            let token = match seg {
+                Segment::Field(name) => name.to_string(),
+                Segment::Index(idx) => idx.to_string(),
+                Segment::Key(key) => key.clone(),
+            };
+            out.push('/');
+            out.push_str(&token.replace('~', "~0").replace('/', "~1"));
+        }
+        out
+    }
+}
+
+impl fmt::Display for DiffPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.0.is_empty() {
+            return write!(f, ".");
+        }
+        for seg in &self.0 {
+            match seg {
+                Segment::Field(name) => write!(f, ".{}", name)?,
+                Segment::Index(idx) => write!(f, "[{}]", idx)?,
+                Segment::Key(key) => write!(f, "[{}]", key)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A single change of a flattened diff, and its location.
+pub type FlatChange<'a> = (DiffPath, DiffRes<&'a dyn Debug>);
+
+/// A diff which can be flattened into a list of changes, each located by its path. Removed and
+/// added values are reported as [`DiffRes::Left`] and [`DiffRes::Right`], and replaced values as
+/// [`DiffRes::Both`]. Unchanged values aren't reported.
+///
+/// This is implemented for the output of the default algorithm of builtin types, and derived
+/// alongside [`Diffable`](crate::Diffable) for the generated diff types.
+pub trait Flatten {
+    /// Append the changes of this diff to `out`, located relative to `path`.
+    fn flatten_into<'a>(&'a self, path: &DiffPath, out: &mut Vec<FlatChange<'a>>);
+
+    /// List the changes of this diff, located relative to the root.
+    fn flatten(&self) -> Vec<FlatChange<'_>> {
+        let mut out = Vec::new();
+        self.flatten_into(&DiffPath::default(), &mut out);
+        out
+    }
+}
+
+fn key<K: Debug + ?Sized>(path: &DiffPath, key: &K) -> DiffPath {
+    path.with(Segment::Key(format!("{:?}", key)))
+}
+
+impl<T: Debug> Flatten for ValueDiff<'_, T> {
+    fn flatten_into<'a>(&'a self, path: &DiffPath, out: &mut Vec<FlatChange<'a>>) {
+        if let ValueDiff::Changed(l, r) = self {
+            out.push((path.clone(), DiffRes::Both(*l, *r)));
+        }
+    }
+}
+
+/// Each removed or added element is located by its index in the side it comes from.
+impl<T: Debug> Flatten for Vec<DiffRes<&[T]>> {
+    fn flatten_into<'a>(&'a self, path: &DiffPath, out: &mut Vec<FlatChange<'a>>) {
+        let (mut l_idx, mut r_idx) = (0, 0);
+        for chunk in self {
+            match chunk {
+                DiffRes::Left(removed) => {
+                    for item in *removed {
+                        out.push((path.with(Segment::Index(l_idx)), DiffRes::Left(item)));
+                        l_idx += 1;
+                    }
+                }
+                DiffRes::Both(both, _) => {
+                    l_idx += both.len();
+                    r_idx += both.len();
+                }
+                DiffRes::Right(added) => {
+                    for item in *added {
+                        out.push((path.with(Segment::Index(r_idx)), DiffRes::Right(item)));
+                        r_idx += 1;
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Each removed or added line is located by its index in the side it comes from.
+impl Flatten for Vec<DiffRes<&str>> {
+    fn flatten_into<'a>(&'a self, path: &DiffPath, out: &mut Vec<FlatChange<'a>>) {
+        let (mut l_idx, mut r_idx) = (0, 0);
+        for line in self {
+            match line {
+                DiffRes::Left(l) => {
+                    out.push((path.with(Segment::Index(l_idx)), DiffRes::Left(l)));
+                    l_idx += 1;
+                }
+                DiffRes::Both(..) => {
+                    l_idx += 1;
+                    r_idx += 1;
+                }
+                DiffRes::Right(r) => {
+                    out.push((path.with(Segment::Index(r_idx)), DiffRes::Right(r)));
+                    r_idx += 1;
+                }
+            }
+        }
+    }
+}
+
+fn flatten_set<'a, 'b: 'a, T: Debug + 'b>(
+    items: impl Iterator<Item = &'a DiffRes<&'b T>>,
+    path: &DiffPath,
+    out: &mut Vec<FlatChange<'a>>,
+) {
+    for item in items {
+        match item {
+            DiffRes::Left(l) => out.push((key(path, l), DiffRes::Left(*l))),
+            DiffRes::Right(r) => out.push((key(path, r), DiffRes::Right(*r))),
+            DiffRes::Both(..) => (),
+        }
+    }
+}
+
+/// Elements of a set are located by themselves, as a key.
+impl<T: Debug> Flatten for Vec<DiffRes<&T>> {
+    fn flatten_into<'a>(&'a self, path: &DiffPath, out: &mut Vec<FlatChange<'a>>) {
+        flatten_set(self.iter(), path, out);
+    }
+}
+
+/// Elements of a set are located by themselves, as a key.
+impl<T: Debug> Flatten for HashSet<DiffRes<&T>> {
+    fn flatten_into<'a>(&'a self, path: &DiffPath, out: &mut Vec<FlatChange<'a>>) {
+        flatten_set(self.iter(), path, out);
+    }
+}
+
+/// Elements are located by themselves, as a key, and reported with their number of occurrences.
+impl<T: Debug> Flatten for Vec<DiffRes<(&T, usize)>> {
+    fn flatten_into<'a>(&'a self, path: &DiffPath, out: &mut Vec<FlatChange<'a>>) {
+        for item in self {
+            match item {
+                DiffRes::Left(l) => out.push((key(path, l.0), DiffRes::Left(l))),
+                DiffRes::Both(l, r) if l.1 != r.1 => {
+                    out.push((key(path, l.0), DiffRes::Both(l, r)))
+                }
+                DiffRes::Both(..) => (),
+                DiffRes::Right(r) => out.push((key(path, r.0), DiffRes::Right(r))),
+            }
+        }
+    }
+}
+
+impl<V: Debug, D: Flatten> Flatten for MapEntry<'_, V, D> {
+    fn flatten_into<'a>(&'a self, path: &DiffPath, out: &mut Vec<FlatChange<'a>>) {
+        match self {
+            MapEntry::Removed(l) => out.push((path.clone(), DiffRes::Left(*l))),
+            MapEntry::Unchanged(_) => (),
+            MapEntry::Changed(d) => d.flatten_into(path, out),
+            MapEntry::Added(r) => out.push((path.clone(), DiffRes::Right(*r))),
+        }
+    }
+}
+
+/// Entries are flattened in the order of their rendered keys, so the output doesn't depend on the
+/// iteration order of the map.
+impl<K: Debug, V: Debug, D: Flatten> Flatten for HashMap<&K, MapEntry<'_, V, D>> {
+    fn flatten_into<'a>(&'a self, path: &DiffPath, out: &mut Vec<FlatChange<'a>>) {
+        let mut entries = self
+            .iter()
+            .map(|(k, entry)| (format!("{:?}", k), entry))
+            .collect::<Vec<_>>();
+        entries.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+        for (k, entry) in entries {
+            entry.flatten_into(&path.with(Segment::Key(k)), out);
+        }
+    }
+}
+
+impl<K: Debug, V: Debug, D: Flatten> Flatten for Vec<(&K, MapEntry<'_, V, D>)> {
+    fn flatten_into<'a>(&'a self, path: &DiffPath, out: &mut Vec<FlatChange<'a>>) {
+        for (k, entry) in self {
+            entry.flatten_into(&key(path, k), out);
+        }
+    }
+}
+
+/// Elements are located by their index in the right input, or the left if they were removed.
+impl<T: Debug, D: Flatten> Flatten for Vec<KeyedEntry<'_, T, D>> {
+    fn flatten_into<'a>(&'a self, path: &DiffPath, out: &mut Vec<FlatChange<'a>>) {
+        for entry in self {
+            let idx = entry.right.or(entry.left).unwrap_or_default();
+            entry
+                .entry
+                .flatten_into(&path.with(Segment::Index(idx)), out);
+        }
+    }
+}
+
+/// The contents of matching variants are located at the same path as the enum itself.
+impl<T: Debug, D: Flatten> Flatten for VariantDiff<'_, T, D> {
+    fn flatten_into<'a>(&'a self, path: &DiffPath, out: &mut Vec<FlatChange<'a>>) {
+        match self {
+            VariantDiff::Same(d) => d.flatten_into(path, out),
+            VariantDiff::Changed(l, r) => out.push((path.clone(), DiffRes::Both(*l, *r))),
+        }
+    }
+}
+
+impl<D: Flatten> Flatten for Option<D> {
+    fn flatten_into<'a>(&'a self, path: &DiffPath, out: &mut Vec<FlatChange<'a>>) {
+        if let Some(d) = self {
+            d.flatten_into(path, out);
+        }
+    }
+}
+
+impl<D: Flatten, E: Flatten> Flatten for Result<D, E> {
+    fn flatten_into<'a>(&'a self, path: &DiffPath, out: &mut Vec<FlatChange<'a>>) {
+        match self {
+            Ok(d) => d.flatten_into(path, out),
+            Err(e) => e.flatten_into(path, out),
+        }
+    }
+}
+
+macro_rules! tuple_flatten {
+    ($(($($name:ident $idx:tt),+)),* $(,)?) => {
+        $(
+        /// Each element is located by its index.
+        impl<$($name: Flatten),+> Flatten for ($($name,)+) {
+            fn flatten_into<'a>(&'a self, path: &DiffPath, out: &mut Vec<FlatChange<'a>>) {
+                $(self.$idx.flatten_into(&path.with(Segment::Index($idx)), out);)+
+            }
+        }
+        )*
+    };
+}
+
+tuple_flatten!(
+    (T0 0),
+    (T0 0, T1 1),
+    (T0 0, T1 1, T2 2),
+    (T0 0, T1 1, T2 2, T3 3),
+    (T0 0, T1 1, T2 2, T3 3, T4 4),
+    (T0 0, T1 1, T2 2, T3 3, T4 4, T5 5),
+    (T0 0, T1 1, T2 2, T3 3, T4 4, T5 5, T6 6),
+    (T0 0, T1 1, T2 2, T3 3, T4 4, T5 5, T6 6, T7 7),
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_flatten() {
+        let a = (
+            vec![1, 2, 3],
+            BTreeMap::from([("x", Some(1)), ("y", None)]),
+            "one\ntwo\n".to_owned(),
+        );
+        let b = (
+            vec![1, 3, 4],
+            BTreeMap::from([("x", Some(2)), ("z", Some(3))]),
+            "one\nthree\n".to_owned(),
+        );
+        let d = crate::diff(&a, &b);
+
+        let changes = d
+            .flatten()
+            .into_iter()
+            .map(|(path, res)| {
+                let res = match res {
+                    DiffRes::Left(l) => format!("-{:?}", l),
+                    DiffRes::Both(l, r) => format!("{:?} -> {:?}", l, r),
+                    DiffRes::Right(r) => format!("+{:?}", r),
+                };
+                format!("{} {}", path, res)
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            changes,
+            vec![
+                "[0][1] -2",
+                "[0][2] +4",
+                "[1][\"x\"] 1 -> 2",
+                "[1][\"y\"] -None",
+                "[1][\"z\"] +Some(3)",
+                "[2][1] -\"two\"",
+                "[2][1] +\"three\"",
+            ],
+        );
+    }
+
+    #[test]
+    fn test_flatten_hashmap() {
+        let a = HashMap::from([("a", 1), ("b", 2), ("c", 3), ("d", 4), ("e", 5)]);
+        let b = HashMap::from([("a", 0), ("b", 2), ("c", 0), ("e", 0), ("f", 6)]);
+        let d = crate::diff(&a, &b);
+
+        let paths = d
+            .flatten()
+            .into_iter()
+            .map(|(path, _)| path.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            paths,
+            vec!["[\"a\"]", "[\"c\"]", "[\"d\"]", "[\"e\"]", "[\"f\"]"],
+        );
+    }
+
+    #[test]
+    fn test_path() {
+        let path = DiffPath(vec![
+            Segment::Field("deps"),
+            Segment::Key("a/b".to_owned()),
+            Segment::Index(2),
+        ]);
+        assert_eq!(path.to_string(), ".deps[a/b][2]");
+        assert_eq!(path.to_pointer(), "/deps/a~1b/2");
+        assert!(path.starts_with(&DiffPath(vec![Segment::Field("deps")])));
+        assert_eq!(DiffPath::default().to_string(), ".");
+    }
+}
@@ -0,0 +1,671 @@
+//! Differences of Rust source files at the level of items. Both sides are parsed with [`syn`], and
+//! items are matched by their kind and name, so reformatting a file or reordering its comments is
+//! not reported as a change.
+
+use crate::algo::DiffAlgo;
+use crate::builtin::LcsDiff;
+use crate::DiffRes;
+use proc_macro2::{Delimiter, Spacing, TokenStream, TokenTree};
+use quote::ToTokens;
+use std::collections::HashMap;
+use std::fmt;
+use std::ops::Range;
+use syn::spanned::Spanned;
+use syn::{Attribute, Block, ImplItem, Item, Signature, TraitItem};
+
+/// Generate an item-level diff of two Rust source files. Items are compared by their tokens, so
+/// formatting is ignored. If `IGNORE_TRIVIA` is true, doc comments are ignored too. Otherwise,
+/// changes to doc comments are reported, as are items whose only changes are formatting or regular
+/// comments.
+pub struct RustDiff<const IGNORE_TRIVIA: bool = true>;
+
+/// The way an item changed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ChangeKind {
+    Added,
+    Removed,
+    /// The item is unchanged, but its position relative to the other items changed.
+    Moved,
+    /// The signature of a function, or the header of an `impl`, `trait` or `mod`, changed.
+    Signature,
+    /// The body of a function changed.
+    Body,
+    /// The attributes of an item changed, including its doc comments.
+    Attributes,
+    /// Some other part of an item changed, such as the fields of a struct.
+    Item,
+    /// Only the formatting or regular comments of an item changed.
+    Formatting,
+}
+
+/// A single change between two Rust source files. The lines are 1-indexed, and present for each
+/// side the item exists in.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Change {
+    /// The item, and any items it's nested in, such as `impl Display for Foo::fmt`.
+    pub path: String,
+    pub kind: ChangeKind,
+    pub left: Option<usize>,
+    pub right: Option<usize>,
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = match self.kind {
+            ChangeKind::Added => "added",
+            ChangeKind::Removed => "removed",
+            ChangeKind::Moved => "moved",
+            ChangeKind::Signature => "signature changed",
+            ChangeKind::Body => "body changed",
+            ChangeKind::Attributes => "attributes changed",
+            ChangeKind::Item => "changed",
+            ChangeKind::Formatting => "formatting changed",
+        };
+        write!(f, "{}: {}", self.path, kind)?;
+        match (self.left, self.right) {
+            (Some(l), Some(r)) => write!(f, " (line {} -> {})", l, r),
+            (Some(line), None) | (None, Some(line)) => write!(f, " (line {})", line),
+            (None, None) => Ok(()),
+        }
+    }
+}
+
+/// An error from parsing a Rust source file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Error {
+    pub line: usize,
+    pub col: usize,
+    pub msg: String,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.line, self.col, self.msg)
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Whether a bracketed attribute body is a doc comment.
+fn is_doc(group: &TokenTree) -> bool {
+    match group {
+        TokenTree::Group(group) if group.delimiter() == Delimiter::Bracket => matches!(
+            group.stream().into_iter().next(),
+            Some(TokenTree::Ident(ident)) if ident == "doc"
+        ),
+        _ => false,
+    }
+}
+
+/// Remove doc comments, which are otherwise parsed as `#[doc]` attributes, from a token stream.
+fn strip_docs(tokens: TokenStream) -> TokenStream {
+    let mut out = Vec::new();
+    let mut iter = tokens.into_iter().peekable();
+    while let Some(tt) = iter.next() {
+        if let TokenTree::Punct(p) = &tt {
+            if p.as_char() == '#' {
+                let inner = matches!(iter.peek(), Some(TokenTree::Punct(p)) if p.as_char() == '!');
+                let mut ahead = iter.clone();
+                if inner {
+                    ahead.next();
+                }
+                if ahead.next().as_ref().is_some_and(is_doc) {
+                    iter = ahead;
+                    continue;
+                }
+            }
+        }
+        match tt {
+            TokenTree::Group(group) => {
+                let mut new =
+                    proc_macro2::Group::new(group.delimiter(), strip_docs(group.stream()));
+                new.set_span(group.span());
+                out.push(TokenTree::Group(new));
+            }
+            tt => out.push(tt),
+        }
+    }
+    out.into_iter().collect()
+}
+
+fn parse(src: &str, ignore_trivia: bool) -> Result<syn::File, Error> {
+    let tokens = src.parse::<TokenStream>().map_err(|err| Error {
+        line: err.span().start().line,
+        col: err.span().start().column + 1,
+        msg: err.to_string(),
+    })?;
+    let tokens = if ignore_trivia {
+        strip_docs(tokens)
+    } else {
+        tokens
+    };
+    syn::parse2(tokens).map_err(|err| Error {
+        line: err.span().start().line,
+        col: err.span().start().column + 1,
+        msg: err.to_string(),
+    })
+}
+
+/// Operators of more than one character. Other runs of joined punctuation, such as the `>,` in
+/// `Vec<u8>, T`, are split into single characters.
+const OPS: [&str; 11] = [
+    "..=", "::", "->", "=>", "==", "!=", "<=", ">=", "&&", "||", "..",
+];
+
+/// Render tokens compactly, for use in names, in the style of rustfmt. This gives
+/// `Vec<(u8, &'a str)>` rather than the `Vec < (u8 , & 'a str) >` of [`TokenStream`]'s `Display`.
+fn compact(tokens: impl ToTokens) -> String {
+    let mut compact = Compact {
+        out: String::new(),
+        space: false,
+        name: false,
+    };
+    compact.tokens(tokens.to_token_stream());
+    compact.out
+}
+
+/// A formatter for [`compact`], which decides the spacing between each pair of tokens.
+struct Compact {
+    out: String,
+    /// Whether the last token may be followed by a space.
+    space: bool,
+    /// Whether the last token was a name or a group, which arguments and indices follow directly.
+    name: bool,
+}
+
+impl Compact {
+    /// Write a token, separated from the last one by a space if both allow it.
+    fn push(&mut self, text: &str, before: bool, after: bool) {
+        if before && self.space {
+            self.out.push(' ');
+        }
+        self.out.push_str(text);
+        self.space = after;
+        self.name = false;
+    }
+
+    fn tokens(&mut self, tokens: TokenStream) {
+        let mut iter = tokens.into_iter().peekable();
+        while let Some(tt) = iter.next() {
+            match tt {
+                TokenTree::Group(group) => {
+                    let (open, close) = match group.delimiter() {
+                        Delimiter::Parenthesis => ("(", ")"),
+                        Delimiter::Bracket => ("[", "]"),
+                        Delimiter::Brace => ("{", "}"),
+                        Delimiter::None => {
+                            self.tokens(group.stream());
+                            continue;
+                        }
+                    };
+                    let before = !self.name || group.delimiter() == Delimiter::Brace;
+                    self.push(open, before, false);
+                    self.tokens(group.stream());
+                    self.push(close, false, true);
+                    self.name = true;
+                }
+                TokenTree::Ident(ident) => {
+                    let text = ident.to_string();
+                    self.push(&text, true, true);
+                    self.name = !matches!(
+                        text.as_str(),
+                        "as" | "dyn" | "for" | "impl" | "in" | "mut" | "where"
+                    );
+                }
+                TokenTree::Literal(lit) => self.push(&lit.to_string(), true, true),
+                TokenTree::Punct(punct) => {
+                    let mut run = String::from(punct.as_char());
+                    let mut spacing = punct.spacing();
+                    while spacing == Spacing::Joint {
+                        match iter.peek() {
+                            Some(TokenTree::Punct(next)) => {
+                                run.push(next.as_char());
+                                spacing = next.spacing();
+                                iter.next();
+                            }
+                            _ => break,
+                        }
+                    }
+                    let mut rest = run.as_str();
+                    while !rest.is_empty() {
+                        let len = OPS
+                            .iter()
+                            .find(|op| rest.starts_with(*op))
+                            .map_or(1, |op| op.len());
+                        let (op, tail) = rest.split_at(len);
+                        let (before, after) = match op {
+                            "::" | "<" | "." | ".." | "..=" | "!" => (false, false),
+                            ">" | "," | ";" | ":" => (false, true),
+                            "&" | "'" | "#" | "?" | "*" => (true, false),
+                            _ => (true, true),
+                        };
+                        self.push(op, before, after);
+                        rest = tail;
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// The tokens of an item, without its outer attributes, which are compared separately.
+fn without_attrs(item: impl ToTokens) -> TokenStream {
+    let mut iter = item.to_token_stream().into_iter().peekable();
+    while matches!(iter.peek(), Some(TokenTree::Punct(p)) if p.as_char() == '#') {
+        iter.next();
+        iter.next();
+    }
+    iter.collect()
+}
+
+/// Render tokens for comparison. Trailing commas are dropped, as formatting may add or remove them.
+fn normalized(tokens: TokenStream) -> String {
+    fn strip(tokens: TokenStream) -> TokenStream {
+        let mut out = tokens
+            .into_iter()
+            .map(|tt| match tt {
+                TokenTree::Group(group) => TokenTree::Group(proc_macro2::Group::new(
+                    group.delimiter(),
+                    strip(group.stream()),
+                )),
+                tt => tt,
+            })
+            .collect::<Vec<_>>();
+        if matches!(out.last(), Some(TokenTree::Punct(p)) if p.as_char() == ',') {
+            out.pop();
+        }
+        out.into_iter().collect()
+    }
+    strip(tokens).to_string()
+}
+
+fn same(l: impl ToTokens, r: impl ToTokens) -> bool {
+    normalized(l.to_token_stream()) == normalized(r.to_token_stream())
+}
+
+/// An item, with the parts that are compared separately broken out.
+struct Entry<'a> {
+    name: String,
+    /// The line the item starts on, after its attributes.
+    line: usize,
+    /// The byte range of the whole item in the source.
+    text: Range<usize>,
+    attrs: &'a [Attribute],
+    kind: EntryKind<'a>,
+}
+
+enum EntryKind<'a> {
+    Fn(&'a Signature, &'a Block),
+    /// An item containing other items, with its header rendered as tokens.
+    Nested(TokenStream, Vec<Entry<'a>>),
+    Other(TokenStream),
+}
+
+impl<'a> Entry<'a> {
+    fn new(
+        name: String,
+        node: &impl ToTokens,
+        attrs: &'a [Attribute],
+        kind: EntryKind<'a>,
+    ) -> Entry<'a> {
+        let start = without_attrs(node)
+            .into_iter()
+            .next()
+            .map_or_else(|| node.span(), |tt| tt.span());
+        Entry {
+            name,
+            line: start.start().line,
+            text: node.span().byte_range(),
+            attrs,
+            kind,
+        }
+    }
+
+    fn other(name: String, node: &impl ToTokens, attrs: &'a [Attribute]) -> Entry<'a> {
+        Entry::new(name, node, attrs, EntryKind::Other(without_attrs(node)))
+    }
+}
+
+fn fn_entry<'a>(
+    node: &impl ToTokens,
+    attrs: &'a [Attribute],
+    sig: &'a Signature,
+    block: &'a Block,
+) -> Entry<'a> {
+    let name = format!("fn {}", sig.ident);
+    Entry::new(name, node, attrs, EntryKind::Fn(sig, block))
+}
+
+fn item_entry(item: &Item) -> Entry<'_> {
+    match item {
+        Item::Fn(func) => fn_entry(func, &func.attrs, &func.sig, &func.block),
+        Item::Impl(imp) => {
+            let header = {
+                let mut imp = imp.clone();
+                imp.items.clear();
+                imp.attrs.clear();
+                imp.into_token_stream()
+            };
+            let name = match &imp.trait_ {
+                Some((bang, path, _)) => format!(
+                    "impl {}{} for {}",
+                    if bang.is_some() { "!" } else { "" },
+                    compact(path),
+                    compact(&imp.self_ty)
+                ),
+                None => format!("impl {}", compact(&imp.self_ty)),
+            };
+            let items = imp
+                .items
+                .iter()
+                .map(|item| match item {
+                    ImplItem::Fn(func) => fn_entry(func, &func.attrs, &func.sig, &func.block),
+                    ImplItem::Const(c) => Entry::other(format!("const {}", c.ident), c, &c.attrs),
+                    ImplItem::Type(ty) => Entry::other(format!("type {}", ty.ident), ty, &ty.attrs),
+                    item => Entry::other(compact(item), item, &[]),
+                })
+                .collect();
+            Entry::new(name, imp, &imp.attrs, EntryKind::Nested(header, items))
+        }
+        Item::Trait(tr) => {
+            let header = {
+                let mut tr = tr.clone();
+                tr.items.clear();
+                tr.attrs.clear();
+                tr.into_token_stream()
+            };
+            let items = tr
+                .items
+                .iter()
+                .map(|item| match item {
+                    TraitItem::Fn(func) => match &func.default {
+                        Some(block) => fn_entry(func, &func.attrs, &func.sig, block),
+                        None => Entry::other(format!("fn {}", func.sig.ident), func, &func.attrs),
+                    },
+                    TraitItem::Const(c) => Entry::other(format!("const {}", c.ident), c, &c.attrs),
+                    TraitItem::Type(ty) => {
+                        Entry::other(format!("type {}", ty.ident), ty, &ty.attrs)
+                    }
+                    item => Entry::other(compact(item), item, &[]),
+                })
+                .collect();
+            let name = format!("trait {}", tr.ident);
+            Entry::new(name, tr, &tr.attrs, EntryKind::Nested(header, items))
+        }
+        Item::Mod(m) => match &m.content {
+            Some((_, items)) => {
+                let items = items.iter().map(item_entry).collect();
+                let kind = EntryKind::Nested(TokenStream::new(), items);
+                Entry::new(format!("mod {}", m.ident), m, &m.attrs, kind)
+            }
+            None => Entry::other(format!("mod {}", m.ident), m, &m.attrs),
+        },
+        Item::Struct(s) => Entry::other(format!("struct {}", s.ident), s, &s.attrs),
+        Item::Enum(e) => Entry::other(format!("enum {}", e.ident), e, &e.attrs),
+        Item::Union(u) => Entry::other(format!("union {}", u.ident), u, &u.attrs),
+        Item::Type(ty) => Entry::other(format!("type {}", ty.ident), ty, &ty.attrs),
+        Item::Const(c) => Entry::other(format!("const {}", c.ident), c, &c.attrs),
+        Item::Static(s) => Entry::other(format!("static {}", s.ident), s, &s.attrs),
+        Item::TraitAlias(t) => Entry::other(format!("trait {}", t.ident), t, &t.attrs),
+        Item::ExternCrate(e) => Entry::other(format!("extern crate {}", e.ident), e, &e.attrs),
+        Item::Use(u) => Entry::other(format!("use {}", compact(&u.tree)), u, &u.attrs),
+        Item::Macro(m) => match &m.ident {
+            Some(ident) => Entry::other(format!("macro_rules! {}", ident), m, &m.attrs),
+            None => Entry::other(format!("{}!", compact(&m.mac.path)), m, &m.attrs),
+        },
+        Item::ForeignMod(f) => Entry::other(format!("extern {}", compact(&f.abi)), f, &f.attrs),
+        item => Entry::other(compact(item), item, &[]),
+    }
+}
+
+/// Number repeated names, so the nth item with a name on one side matches the nth on the other.
+fn keyed<'e, 'a>(entries: &'e [Entry<'a>]) -> Vec<((&'e str, usize), &'e Entry<'a>)> {
+    let mut seen = HashMap::new();
+    entries
+        .iter()
+        .map(|entry| {
+            let count = seen.entry(entry.name.as_str()).or_insert(0);
+            *count += 1;
+            ((entry.name.as_str(), *count), entry)
+        })
+        .collect()
+}
+
+struct Differ<'s> {
+    l_src: &'s str,
+    r_src: &'s str,
+    ignore_trivia: bool,
+    out: Vec<Change>,
+}
+
+impl Differ<'_> {
+    fn push(&mut self, path: &str, kind: ChangeKind, l: Option<&Entry>, r: Option<&Entry>) {
+        self.out.push(Change {
+            path: path.to_owned(),
+            kind,
+            left: l.map(|e| e.line),
+            right: r.map(|e| e.line),
+        });
+    }
+
+    fn path(prefix: &str, name: &str) -> String {
+        if prefix.is_empty() {
+            name.to_owned()
+        } else {
+            format!("{}::{}", prefix, name)
+        }
+    }
+
+    fn entries(&mut self, prefix: &str, l: &[Entry], r: &[Entry]) {
+        let l_keyed = keyed(l);
+        let r_keyed = keyed(r);
+        let r_map = r_keyed.iter().copied().collect::<HashMap<_, _>>();
+        let l_map = l_keyed.iter().copied().collect::<HashMap<_, _>>();
+
+        // Items present on both sides that aren't part of the longest common ordering were moved
+        let l_common = l_keyed
+            .iter()
+            .filter(|(key, _)| r_map.contains_key(key))
+            .map(|(key, _)| *key)
+            .collect::<Vec<_>>();
+        let r_common = r_keyed
+            .iter()
+            .filter(|(key, _)| l_map.contains_key(key))
+            .map(|(key, _)| *key)
+            .collect::<Vec<_>>();
+        let mut moved = Vec::new();
+        for chunk in LcsDiff::diff(&l_common[..], &r_common[..]) {
+            if let DiffRes::Right(keys) = chunk {
+                moved.extend_from_slice(keys);
+            }
+        }
+
+        for (key, le) in &l_keyed {
+            let path = Self::path(prefix, key.0);
+            match r_map.get(key) {
+                Some(re) => {
+                    if moved.contains(key) {
+                        self.push(&path, ChangeKind::Moved, Some(le), Some(re));
+                    }
+                    self.entry(&path, le, re);
+                }
+                None => self.push(&path, ChangeKind::Removed, Some(le), None),
+            }
+        }
+        for (key, re) in &r_keyed {
+            if !l_map.contains_key(key) {
+                self.push(
+                    &Self::path(prefix, key.0),
+                    ChangeKind::Added,
+                    None,
+                    Some(re),
+                );
+            }
+        }
+    }
+
+    fn entry(&mut self, path: &str, l: &Entry, r: &Entry) {
+        let before = self.out.len();
+        let same_attrs =
+            l.attrs.len() == r.attrs.len() && l.attrs.iter().zip(r.attrs).all(|(l, r)| same(l, r));
+        if !same_attrs {
+            self.push(path, ChangeKind::Attributes, Some(l), Some(r));
+        }
+        match (&l.kind, &r.kind) {
+            (EntryKind::Fn(ls, lb), EntryKind::Fn(rs, rb)) => {
+                if !same(ls, rs) {
+                    self.push(path, ChangeKind::Signature, Some(l), Some(r));
+                }
+                if !same(lb, rb) {
+                    self.push(path, ChangeKind::Body, Some(l), Some(r));
+                }
+            }
+            (EntryKind::Nested(lh, li), EntryKind::Nested(rh, ri)) => {
+                if !same(lh, rh) {
+                    self.push(path, ChangeKind::Signature, Some(l), Some(r));
+                }
+                self.entries(path, li, ri);
+                return;
+            }
+            (EntryKind::Other(lt), EntryKind::Other(rt)) if same(lt, rt) => (),
+            _ => self.push(path, ChangeKind::Item, Some(l), Some(r)),
+        }
+        if !self.ignore_trivia && self.out.len() == before {
+            let l_text = self.l_src.get(l.text.clone());
+            let r_text = self.r_src.get(r.text.clone());
+            if l_text != r_text {
+                self.push(path, ChangeKind::Formatting, Some(l), Some(r));
+            }
+        }
+    }
+}
+
+impl<const IGNORE_TRIVIA: bool> DiffAlgo<str> for RustDiff<IGNORE_TRIVIA> {
+    type Diff<'a> = Result<Vec<Change>, Error>;
+
+    fn diff<'a>(l: &'a str, r: &'a str) -> Self::Diff<'a> {
+        let l_file = parse(l, IGNORE_TRIVIA)?;
+        let r_file = parse(r, IGNORE_TRIVIA)?;
+        let l_entries = l_file.items.iter().map(item_entry).collect::<Vec<_>>();
+        let r_entries = r_file.items.iter().map(item_entry).collect::<Vec<_>>();
+
+        let mut differ = Differ {
+            l_src: l,
+            r_src: r,
+            ignore_trivia: IGNORE_TRIVIA,
+            out: Vec::new(),
+        };
+        differ.entries("", &l_entries, &r_entries);
+        Ok(differ.out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Diffable;
+
+    const LEFT: &str = r#"
+/// A point.
+struct Point { x: i32, y: i32 }
+
+impl Point {
+    fn new(x: i32, y: i32) -> Point { Point { x, y } }
+
+    fn len(&self) -> f64 {
+        ((self.x * self.x + self.y * self.y) as f64).sqrt()
+    }
+}
+
+fn helper() {}
+
+impl std::fmt::Display for Point {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "({}, {})", self.x, self.y)
+    }
+}
+"#;
+
+    const RIGHT: &str = r#"
+/// A point in 2D space.
+struct Point {
+    x: i32,
+    y: i32,
+}
+
+impl std::fmt::Display for Point {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // Written as a tuple
+        write!(f, "({}, {})", self.x, self.y)
+    }
+}
+
+impl Point {
+    fn new(x: i32, y: i32) -> Point {
+        Point { x, y }
+    }
+
+    fn len(&self) -> f32 {
+        ((self.x * self.x + self.y * self.y) as f32).sqrt()
+    }
+
+    fn origin() -> Point {
+        Point::new(0, 0)
+    }
+}
+"#;
+
+    #[test]
+    fn test_rust() {
+        let d = LEFT.diff::<RustDiff>(RIGHT).unwrap();
+
+        let lines = d.iter().map(Change::to_string).collect::<Vec<_>>();
+        assert_eq!(
+            lines,
+            vec![
+                "impl Point: moved (line 5 -> 15)",
+                "impl Point::fn len: signature changed (line 8 -> 20)",
+                "impl Point::fn len: body changed (line 8 -> 20)",
+                "impl Point::fn origin: added (line 24)",
+                "fn helper: removed (line 13)",
+            ],
+        );
+    }
+
+    #[test]
+    fn test_compact() {
+        let names = [
+            "std::collections::HashMap<&'a str, Vec<(u8, [u16; 4])>>",
+            "Box<dyn Fn(u8) -> Option<u8> + Send>",
+            "<T as Iterator>::Item",
+            "impl for<'a> Fn(&'a T) -> &'a T",
+            "*const T",
+        ];
+        for name in names {
+            let ty = syn::parse_str::<syn::Type>(name).unwrap();
+            assert_eq!(compact(ty), name);
+        }
+        let tree = syn::parse_str::<syn::UseTree>("std::{fmt, io::Write as _}").unwrap();
+        assert_eq!(compact(tree), "std::{fmt, io::Write as _}");
+    }
+
+    #[test]
+    fn test_rust_trivia() {
+        let d = LEFT.diff::<RustDiff<false>>(RIGHT).unwrap();
+
+        let lines = d.iter().map(Change::to_string).collect::<Vec<_>>();
+        assert_eq!(
+            lines,
+            vec![
+                "struct Point: attributes changed (line 3 -> 3)",
+                "impl Point: moved (line 5 -> 15)",
+                "impl Point::fn new: formatting changed (line 6 -> 16)",
+                "impl Point::fn len: signature changed (line 8 -> 20)",
+                "impl Point::fn len: body changed (line 8 -> 20)",
+                "impl Point::fn origin: added (line 24)",
+                "fn helper: removed (line 13)",
+                "impl std::fmt::Display for Point::fn fmt: formatting changed (line 16 -> 9)",
+            ],
+        );
+    }
+}
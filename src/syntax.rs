@@ -0,0 +1,386 @@
+//! Syntax-aware differences of source code, using [`tree_sitter`] grammars. Both sides are parsed,
+//! and their syntax trees compared with [`TreeDiff`], so changes to whitespace and layout within a
+//! line aren't reported. The output is per line, in the same form as [`LcsDiff`] on strings, so can
+//! be rendered with [`fmt_string`](crate::fmt::fmt_string).
+
+use crate::algo::DiffAlgo;
+use crate::builtin::LcsDiff;
+use crate::tree::{NodePath, Tree, TreeDiff, TreeEdit};
+use crate::DiffRes;
+use std::marker::PhantomData;
+use std::ops::Range;
+use tree_sitter::{Node, Parser};
+
+/// Generate a line diff of two source files, deciding which lines changed by diffing their syntax
+/// trees in the language `G`. If either side fails to parse, or has more than [`MAX_TOKENS`]
+/// tokens, this falls back to a plain [`LcsDiff`] of the lines, as [`TreeDiff`] is too slow for
+/// large trees.
+pub struct SyntaxDiff<G>(PhantomData<G>);
+
+/// The most tokens either side of a [`SyntaxDiff`] can have before it falls back to [`LcsDiff`].
+pub const MAX_TOKENS: usize = 1000;
+
+/// A language which can be parsed by [`SyntaxDiff`].
+pub trait Grammar {
+    /// Get the [`tree_sitter`] language to parse sources with.
+    fn language() -> tree_sitter::Language;
+}
+
+/// The Rust grammar.
+pub struct Rust;
+/// The Python grammar.
+pub struct Python;
+/// The TypeScript grammar.
+pub struct TypeScript;
+/// A SQL grammar covering most common dialects.
+pub struct Sql;
+
+impl Grammar for Rust {
+    fn language() -> tree_sitter::Language {
+        tree_sitter_rust::LANGUAGE.into()
+    }
+}
+
+impl Grammar for Python {
+    fn language() -> tree_sitter::Language {
+        tree_sitter_python::LANGUAGE.into()
+    }
+}
+
+impl Grammar for TypeScript {
+    fn language() -> tree_sitter::Language {
+        tree_sitter_typescript::LANGUAGE_TYPESCRIPT.into()
+    }
+}
+
+impl Grammar for Sql {
+    fn language() -> tree_sitter::Language {
+        tree_sitter_sequel::LANGUAGE.into()
+    }
+}
+
+struct Token<'a> {
+    text: &'a str,
+    /// The first and last rows this token covers.
+    rows: (usize, usize),
+}
+
+/// A node of a syntax tree, with its children collected so it can be diffed as a [`Tree`].
+/// [`tree_sitter::Node`] has neither its children as a slice nor its text, so can't be one itself.
+struct SyntaxNode<'a> {
+    kind: &'static str,
+    /// The text of a token, or `None` for a node compared by its children.
+    text: Option<&'a str>,
+    /// The tokens this node covers, as indices into the tokens of the whole tree.
+    tokens: Range<usize>,
+    children: Vec<SyntaxNode<'a>>,
+}
+
+impl Tree for SyntaxNode<'_> {
+    type Label<'a> = (&'static str, Option<&'a str>)
+    where
+        Self: 'a;
+
+    fn label(&self) -> Self::Label<'_> {
+        (self.kind, self.text)
+    }
+
+    fn children(&self) -> &[Self] {
+        &self.children
+    }
+}
+
+impl SyntaxNode<'_> {
+    fn at(&self, path: &NodePath) -> &Self {
+        path.0.iter().fold(self, |node, &idx| &node.children[idx])
+    }
+}
+
+/// Whether a node should be compared as a single token, even if the grammar gives it children.
+fn is_atomic(node: Node<'_>) -> bool {
+    node.child_count() == 0 || node.kind().contains("string") || node.kind().contains("comment")
+}
+
+fn collect<'a>(
+    src: &'a str,
+    node: Node<'_>,
+    tokens: &mut Vec<Token<'a>>,
+) -> Option<SyntaxNode<'a>> {
+    let start = tokens.len();
+    if is_atomic(node) {
+        if node.byte_range().is_empty() {
+            return None;
+        }
+        let text = &src[node.byte_range()];
+        tokens.push(Token {
+            text,
+            rows: (node.start_position().row, node.end_position().row),
+        });
+        return Some(SyntaxNode {
+            kind: node.kind(),
+            text: Some(text),
+            tokens: start..start + 1,
+            children: Vec::new(),
+        });
+    }
+    let mut cursor = node.walk();
+    let children = node
+        .children(&mut cursor)
+        .filter_map(|child| collect(src, child, tokens))
+        .collect();
+    Some(SyntaxNode {
+        kind: node.kind(),
+        text: None,
+        tokens: start..tokens.len(),
+        children,
+    })
+}
+
+fn parse<G: Grammar>(src: &str) -> Option<(SyntaxNode<'_>, Vec<Token<'_>>)> {
+    let mut parser = Parser::new();
+    parser.set_language(&G::language()).ok()?;
+    let tree = parser.parse(src, None)?;
+    if tree.root_node().has_error() {
+        return None;
+    }
+    let mut tokens = Vec::new();
+    let root = collect(src, tree.root_node(), &mut tokens)?;
+    Some((root, tokens))
+}
+
+/// What a line is compared by. Lines touched by a changed token never compare equal.
+enum LineKey<'a> {
+    Same(Vec<&'a str>),
+    Changed,
+}
+
+impl PartialEq for LineKey<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (LineKey::Same(l), LineKey::Same(r)) => l == r,
+            _ => false,
+        }
+    }
+}
+
+fn line_keys<'a>(lines: &[&'a str], tokens: &[Token<'a>], changed: &[bool]) -> Vec<LineKey<'a>> {
+    let mut keys = lines
+        .iter()
+        .map(|line| {
+            let trimmed = line.trim();
+            LineKey::Same(if trimmed.is_empty() {
+                Vec::new()
+            } else {
+                vec![trimmed]
+            })
+        })
+        .collect::<Vec<_>>();
+    let mut started = vec![false; lines.len()];
+    for (token, &changed) in tokens.iter().zip(changed) {
+        let (start, end) = token.rows;
+        if changed {
+            for key in keys.iter_mut().take(end + 1).skip(start) {
+                *key = LineKey::Changed;
+            }
+            continue;
+        }
+        // Lines are compared by the tokens starting on them, rather than their text
+        if let Some(LineKey::Same(key)) = keys.get_mut(start) {
+            if !started[start] {
+                key.clear();
+                started[start] = true;
+            }
+            key.push(token.text);
+        }
+    }
+    keys
+}
+
+impl<G: Grammar> DiffAlgo<str> for SyntaxDiff<G> {
+    type Diff<'a> = Vec<DiffRes<&'a str>>;
+
+    fn diff<'a>(l: &'a str, r: &'a str) -> Self::Diff<'a> {
+        let (Some((l_root, l_tokens)), Some((r_root, r_tokens))) = (parse::<G>(l), parse::<G>(r))
+        else {
+            return LcsDiff::diff(l, r);
+        };
+        if l_tokens.len() > MAX_TOKENS || r_tokens.len() > MAX_TOKENS {
+            return LcsDiff::diff(l, r);
+        }
+
+        // Mark the tokens of every removed, added or moved subtree, and of every updated token. Other
+        // nodes have no text of their own, so changes to them alone don't change any line.
+        let mut l_changed = vec![false; l_tokens.len()];
+        let mut r_changed = vec![false; r_tokens.len()];
+        for change in TreeDiff::diff(&l_root, &r_root) {
+            match change.edit {
+                TreeEdit::Delete(node) => l_changed[node.tokens.clone()].fill(true),
+                TreeEdit::Insert(node) => r_changed[node.tokens.clone()].fill(true),
+                TreeEdit::Update(l_node, r_node) => {
+                    if l_node.text.is_some() {
+                        l_changed[l_node.tokens.clone()].fill(true);
+                    }
+                    if r_node.text.is_some() {
+                        r_changed[r_node.tokens.clone()].fill(true);
+                    }
+                }
+                TreeEdit::Move { from, node } => {
+                    l_changed[l_root.at(&from).tokens.clone()].fill(true);
+                    r_changed[node.tokens.clone()].fill(true);
+                }
+                TreeEdit::Unwrap(_) | TreeEdit::Wrap(_) => (),
+            }
+        }
+
+        let l_lines = l.lines().collect::<Vec<_>>();
+        let r_lines = r.lines().collect::<Vec<_>>();
+        let l_keys = line_keys(&l_lines, &l_tokens, &l_changed);
+        let r_keys = line_keys(&r_lines, &r_tokens, &r_changed);
+
+        let mut out = Vec::new();
+        let (mut l_idx, mut r_idx) = (0, 0);
+        for chunk in LcsDiff::diff(&l_keys[..], &r_keys[..]) {
+            match chunk {
+                DiffRes::Left(removed) => {
+                    out.extend(
+                        l_lines[l_idx..][..removed.len()]
+                            .iter()
+                            .map(|&line| DiffRes::Left(line)),
+                    );
+                    l_idx += removed.len();
+                }
+                DiffRes::Both(both, _) => {
+                    let pairs = l_lines[l_idx..]
+                        .iter()
+                        .zip(&r_lines[r_idx..])
+                        .take(both.len());
+                    out.extend(pairs.map(|(&l, &r)| DiffRes::Both(l, r)));
+                    l_idx += both.len();
+                    r_idx += both.len();
+                }
+                DiffRes::Right(added) => {
+                    out.extend(
+                        r_lines[r_idx..][..added.len()]
+                            .iter()
+                            .map(|&line| DiffRes::Right(line)),
+                    );
+                    r_idx += added.len();
+                }
+            }
+        }
+        // Match the handling of a trailing newline by `LcsDiff`
+        match (l.ends_with('\n'), r.ends_with('\n')) {
+            (true, true) => out.push(DiffRes::Both(&l[l.len()..], &r[r.len()..])),
+            (true, false) => out.push(DiffRes::Left(&l[l.len()..])),
+            (false, true) => out.push(DiffRes::Right(&r[r.len()..])),
+            (false, false) => (),
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Diffable;
+
+    #[test]
+    fn test_syntax() {
+        let a = "def add(a, b):\n    return a+b\n\nx = add(1, 2)\n";
+        let b = "def add(a, b):\n    return a + b\n\nx = add(1, 3)\n";
+        let d = a.diff::<SyntaxDiff<Python>>(b);
+
+        assert_eq!(
+            d,
+            vec![
+                DiffRes::Both("def add(a, b):", "def add(a, b):"),
+                DiffRes::Both("    return a+b", "    return a + b"),
+                DiffRes::Both("", ""),
+                DiffRes::Left("x = add(1, 2)"),
+                DiffRes::Right("x = add(1, 3)"),
+                DiffRes::Both("", ""),
+            ],
+        );
+
+        let mut out = String::new();
+        crate::fmt::fmt_string(d, &mut out).unwrap();
+        assert_eq!(
+            out,
+            " def add(a, b):\n     return a+b\n \n-x = add(1, 2)\n+x = add(1, 3)\n \n"
+        );
+    }
+
+    #[test]
+    fn test_syntax_languages() {
+        let d =
+            "fn main() { let x = 1; }\n".diff::<SyntaxDiff<Rust>>("fn main() {  let x = 1;  }\n");
+        assert!(matches!(
+            d[..],
+            [DiffRes::Both(_, _), DiffRes::Both("", "")]
+        ));
+
+        let d = "let x: number = 1;\n".diff::<SyntaxDiff<TypeScript>>("let x: string = 1;\n");
+        assert!(matches!(
+            d[..],
+            [DiffRes::Left(_), DiffRes::Right(_), DiffRes::Both("", "")]
+        ));
+
+        let d = "SELECT a FROM t;\n".diff::<SyntaxDiff<Sql>>("SELECT  a  FROM t;\n");
+        assert!(matches!(
+            d[..],
+            [DiffRes::Both(_, _), DiffRes::Both("", "")]
+        ));
+
+        let d = "SELECT a FROM t;\n".diff::<SyntaxDiff<Sql>>("SELECT b FROM t;\n");
+        assert!(matches!(
+            d[..],
+            [DiffRes::Left(_), DiffRes::Right(_), DiffRes::Both("", "")]
+        ));
+    }
+
+    #[test]
+    fn test_syntax_wrap() {
+        let a = "fn f() {\n    g();\n    h();\n}\n";
+        let b = "fn f() {\n    if x {\n        g();\n    }\n    h();\n}\n";
+        let d = a.diff::<SyntaxDiff<Rust>>(b);
+
+        assert_eq!(
+            d,
+            vec![
+                DiffRes::Both("fn f() {", "fn f() {"),
+                DiffRes::Right("    if x {"),
+                DiffRes::Both("    g();", "        g();"),
+                DiffRes::Right("    }"),
+                DiffRes::Both("    h();", "    h();"),
+                DiffRes::Both("}", "}"),
+                DiffRes::Both("", ""),
+            ],
+        );
+    }
+
+    #[test]
+    fn test_syntax_newline() {
+        let d = "x = 1\n".diff::<SyntaxDiff<Python>>("x  =  1");
+        assert_eq!(
+            d,
+            vec![DiffRes::Both("x = 1", "x  =  1"), DiffRes::Left("")]
+        );
+
+        let d = "x = 1".diff::<SyntaxDiff<Python>>("x = 1\n");
+        assert_eq!(d, vec![DiffRes::Both("x = 1", "x = 1"), DiffRes::Right("")]);
+    }
+
+    #[test]
+    fn test_syntax_fallback() {
+        let a = "fn main() {\n";
+        let b = "fn main() {\n}\n";
+        assert_eq!(a.diff::<SyntaxDiff<Rust>>(b), LcsDiff::diff(a, b));
+
+        // Too many tokens to diff as trees, so the reformatted line is reported
+        let a = "x = 1\n".repeat(MAX_TOKENS);
+        let b = format!("x  =  1\n{}", a);
+        let d = a.diff::<SyntaxDiff<Python>>(&b);
+        assert_eq!(d, LcsDiff::diff(&a[..], &b[..]));
+        assert_eq!(d[0], DiffRes::Right("x  =  1"));
+    }
+}
@@ -0,0 +1,383 @@
+//! Differences of TOML documents. Both sides are parsed and compared by value, so formatting,
+//! comments, and the order of keys don't matter. Each change is located by its key path, and refers
+//! back to the line and original text it came from.
+
+use crate::algo::DiffAlgo;
+use crate::builtin::diff_paired;
+use crate::{algo, DiffRes, Diffable};
+use std::fmt;
+use std::ops::Range;
+use toml_edit::{ImDocument, Item, TableLike};
+
+/// Generate a semantic diff of two TOML documents. Only changed values are reported.
+pub struct TomlDiff;
+
+/// A parsed TOML value, alongside the span of source text it was parsed from. Tables which are only
+/// defined implicitly, by a dotted key or a header for a sub-table, span their key instead.
+#[derive(Debug, Clone)]
+pub struct Node {
+    pub span: Range<usize>,
+    pub value: Value,
+}
+
+/// The value of a TOML node. Inline tables and arrays of tables are represented the same as other
+/// tables and arrays.
+#[derive(Debug, Clone)]
+pub enum Value {
+    String(String),
+    Integer(i64),
+    Float(f64),
+    Boolean(bool),
+    Datetime(String),
+    Array(Vec<Node>),
+    Table(Vec<(String, Node)>),
+}
+
+impl Value {
+    fn get(&self, key: &str) -> Option<&Node> {
+        match self {
+            Value::Table(entries) => entries.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+}
+
+/// Nodes are equal if their values are, regardless of where they appear in the source.
+impl PartialEq for Node {
+    fn eq(&self, other: &Self) -> bool {
+        self.value == other.value
+    }
+}
+
+/// Tables are equal if they have the same entries, in any order.
+impl PartialEq for Value {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Value::String(l), Value::String(r)) => l == r,
+            (Value::Integer(l), Value::Integer(r)) => l == r,
+            (Value::Float(l), Value::Float(r)) => l == r,
+            (Value::Boolean(l), Value::Boolean(r)) => l == r,
+            (Value::Datetime(l), Value::Datetime(r)) => l == r,
+            (Value::Array(l), Value::Array(r)) => l == r,
+            (Value::Table(l), Value::Table(r)) => {
+                l.len() == r.len() && l.iter().all(|(k, v)| other.get(k) == Some(v))
+            }
+            _ => false,
+        }
+    }
+}
+
+/// A parsed TOML document, which keeps its source text so changes can refer back to it.
+#[derive(Debug, Clone)]
+pub struct Document {
+    src: String,
+    root: Node,
+}
+
+impl Document {
+    /// The source text of this document.
+    pub fn src(&self) -> &str {
+        &self.src
+    }
+
+    /// The root table of this document.
+    pub fn root(&self) -> &Node {
+        &self.root
+    }
+
+    /// The 1-indexed line containing a byte offset of the source text.
+    pub fn line(&self, offset: usize) -> usize {
+        self.src[..offset].matches('\n').count() + 1
+    }
+
+    fn span<'a>(&'a self, node: &'a Node) -> Span<'a> {
+        Span {
+            line: self.line(node.span.start),
+            text: &self.src[node.span.clone()],
+            node,
+        }
+    }
+}
+
+/// Documents are equal if their values are, regardless of formatting.
+impl PartialEq for Document {
+    fn eq(&self, other: &Self) -> bool {
+        self.root == other.root
+    }
+}
+
+/// An error from parsing a TOML document.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Error {
+    pub line: usize,
+    pub msg: String,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.line, self.msg)
+    }
+}
+
+impl std::error::Error for Error {}
+
+fn convert_value(val: &toml_edit::Value, span: Range<usize>) -> Node {
+    let value = match val {
+        toml_edit::Value::String(s) => Value::String(s.value().clone()),
+        toml_edit::Value::Integer(i) => Value::Integer(*i.value()),
+        toml_edit::Value::Float(f) => Value::Float(*f.value()),
+        toml_edit::Value::Boolean(b) => Value::Boolean(*b.value()),
+        toml_edit::Value::Datetime(d) => Value::Datetime(d.value().to_string()),
+        toml_edit::Value::Array(arr) => Value::Array(
+            arr.iter()
+                .map(|v| convert_value(v, v.span().unwrap_or(span.clone())))
+                .collect(),
+        ),
+        toml_edit::Value::InlineTable(table) => convert_table(table, span.clone()),
+    };
+    Node { span, value }
+}
+
+fn convert_table(table: &dyn TableLike, span: Range<usize>) -> Value {
+    Value::Table(
+        table
+            .iter()
+            .map(|(key, item)| {
+                let key_span = table
+                    .get_key_value(key)
+                    .and_then(|(key, _)| key.span())
+                    .unwrap_or(span.clone());
+                (key.to_owned(), convert_item(item, key_span))
+            })
+            .collect(),
+    )
+}
+
+/// Convert an item, falling back to the given span if it doesn't have one of its own.
+fn convert_item(item: &Item, fallback: Range<usize>) -> Node {
+    let span = item.span().unwrap_or(fallback);
+    match item {
+        Item::Value(val) => convert_value(val, span),
+        Item::Table(table) => Node {
+            value: convert_table(table, span.clone()),
+            span,
+        },
+        Item::ArrayOfTables(arr) => Node {
+            value: Value::Array(
+                arr.iter()
+                    .map(|table| Node {
+                        value: convert_table(table, span.clone()),
+                        span: table.span().unwrap_or(span.clone()),
+                    })
+                    .collect(),
+            ),
+            span,
+        },
+        Item::None => unreachable!("parsed documents don't contain empty items"),
+    }
+}
+
+/// Parse a TOML document.
+pub fn parse(src: &str) -> Result<Document, Error> {
+    let doc = ImDocument::parse(src).map_err(|err| Error {
+        line: err
+            .span()
+            .map_or(1, |span| src[..span.start].matches('\n').count() + 1),
+        msg: err.message().to_owned(),
+    })?;
+    let root = Node {
+        value: convert_table(doc.as_table(), 0..0),
+        span: 0..src.len(),
+    };
+    Ok(Document {
+        src: src.to_owned(),
+        root,
+    })
+}
+
+/// A node in the context of its document: the line it starts on and its original text.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Span<'a> {
+    /// The 1-indexed line this node starts on.
+    pub line: usize,
+    /// The source text of this node, exactly as written.
+    pub text: &'a str,
+    pub node: &'a Node,
+}
+
+/// A single difference between two TOML documents. [`DiffRes::Left`] is a removed value, located by
+/// its path in the left document. [`DiffRes::Right`] is an added value, and [`DiffRes::Both`] a
+/// changed value, both located by their path in the right document.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Change<'a> {
+    pub path: String,
+    pub res: DiffRes<Span<'a>>,
+}
+
+impl fmt::Display for Change<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.res {
+            DiffRes::Left(l) => write!(f, "-{} (line {}): {}", self.path, l.line, l.text),
+            DiffRes::Both(l, r) => write!(
+                f,
+                " {} (line {} -> {}): {} -> {}",
+                self.path, l.line, r.line, l.text, r.text
+            ),
+            DiffRes::Right(r) => write!(f, "+{} (line {}): {}", self.path, r.line, r.text),
+        }
+    }
+}
+
+fn key_path(path: &str, key: &str) -> String {
+    let bare = !key.is_empty()
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    match (path.is_empty(), bare) {
+        (true, true) => key.to_owned(),
+        (true, false) => format!("{:?}", key),
+        (false, true) => format!("{}.{}", path, key),
+        (false, false) => format!("{}.{:?}", path, key),
+    }
+}
+
+struct Differ<'a> {
+    l: &'a Document,
+    r: &'a Document,
+    out: Vec<Change<'a>>,
+}
+
+impl<'a> Differ<'a> {
+    fn push(&mut self, path: String, res: DiffRes<&'a Node>) {
+        let res = match res {
+            DiffRes::Left(l) => DiffRes::Left(self.l.span(l)),
+            DiffRes::Both(l, r) => DiffRes::Both(self.l.span(l), self.r.span(r)),
+            DiffRes::Right(r) => DiffRes::Right(self.r.span(r)),
+        };
+        self.out.push(Change { path, res });
+    }
+
+    fn diff(&mut self, path: &str, l: &'a Node, r: &'a Node) {
+        match (&l.value, &r.value) {
+            (Value::Table(lt), Value::Table(rt)) => {
+                for (key, lv) in lt {
+                    let path = key_path(path, key);
+                    match r.value.get(key) {
+                        Some(rv) => self.diff(&path, lv, rv),
+                        None => self.push(path, DiffRes::Left(lv)),
+                    }
+                }
+                for (key, rv) in rt {
+                    if l.value.get(key).is_none() {
+                        self.push(key_path(path, key), DiffRes::Right(rv));
+                    }
+                }
+            }
+            (Value::Array(la), Value::Array(ra)) => {
+                for (idx, res) in diff_paired(la, ra) {
+                    let path = format!("{}[{}]", path, idx);
+                    match res {
+                        DiffRes::Both(lv, rv) => self.diff(&path, lv, rv),
+                        res => self.push(path, res),
+                    }
+                }
+            }
+            _ if l != r => self.push(path.to_owned(), DiffRes::Both(l, r)),
+            _ => (),
+        }
+    }
+}
+
+impl DiffAlgo<Document> for TomlDiff {
+    type Diff<'a> = Vec<Change<'a>>;
+
+    fn diff<'a>(l: &'a Document, r: &'a Document) -> Self::Diff<'a> {
+        let mut differ = Differ {
+            l,
+            r,
+            out: Vec::new(),
+        };
+        differ.diff("", &l.root, &r.root);
+        differ.out
+    }
+}
+
+impl DiffAlgo<Document> for algo::Default {
+    type Diff<'a> = Vec<Change<'a>>;
+
+    fn diff<'a>(l: &'a Document, r: &'a Document) -> Self::Diff<'a> {
+        TomlDiff::diff(l, r)
+    }
+}
+
+impl Diffable for Document {
+    type Diff<'a, A: DiffAlgo<Self::Item>> = A::Diff<'a>;
+    type Item = Document;
+
+    fn diff<'a, A: DiffAlgo<Self::Item>>(&'a self, other: &'a Self) -> A::Diff<'a> {
+        A::diff(self, other)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_toml() {
+        let a = parse(
+            r#"
+[package]
+name = "semdiff"
+version = "0.1.0"   # first release
+edition = "2018"
+
+[dependencies]
+diff = "0.1"
+image = { version = "0.24", optional = true }
+
+[[bin]]
+name = "semdiff"
+"#,
+        )
+        .unwrap();
+        let b = parse(
+            r#"
+[package]
+version = "0.2.0"
+name = 'semdiff'
+edition = "2018"
+
+[dependencies]
+diff = "0.1"
+image = { version = "0.25", optional = true }
+"serde json" = "1.0"
+
+[[bin]]
+name = "semdiff"
+
+[[bin]]
+name = "semdiff-cli"
+"#,
+        )
+        .unwrap();
+        let d = crate::diff(&a, &b);
+
+        let lines = d.iter().map(Change::to_string).collect::<Vec<_>>();
+        assert_eq!(
+            lines,
+            vec![
+                " package.version (line 4 -> 3): \"0.1.0\" -> \"0.2.0\"",
+                " dependencies.image.version (line 9 -> 9): \"0.24\" -> \"0.25\"",
+                "+dependencies.\"serde json\" (line 10): \"1.0\"",
+                "+bin[1] (line 15): [[bin]]\nname = \"semdiff-cli\"",
+            ],
+        );
+    }
+
+    #[test]
+    fn test_toml_error() {
+        let err = parse("a = 1\nb = \n").unwrap_err();
+        assert_eq!(err.line, 2);
+    }
+}
@@ -0,0 +1,441 @@
+//! Differences of ordered, labelled trees. [`TreeDiff`] finds a minimal edit script using the
+//! Zhang–Shasha tree edit distance algorithm, then reports any deleted subtree that reappears
+//! unchanged elsewhere as a move.
+
+use crate::algo::DiffAlgo;
+use std::collections::HashMap;
+use std::fmt;
+
+/// Generate an edit script between two trees using the Zhang–Shasha algorithm. This is
+/// `O(n² m²)` in the worst case, so is best suited to moderately sized trees.
+pub struct TreeDiff;
+
+/// An ordered tree, where each node has a label and a list of children.
+pub trait Tree: Sized {
+    /// The part of a node compared to decide if it was updated. This may borrow from the node.
+    type Label<'a>: PartialEq
+    where
+        Self: 'a;
+
+    /// Get the label of this node.
+    fn label(&self) -> Self::Label<'_>;
+
+    /// Get the children of this node, in order.
+    fn children(&self) -> &[Self];
+}
+
+/// The location of a node, as the index of each child taken from the root.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+pub struct NodePath(pub Vec<usize>);
+
+impl fmt::Display for NodePath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.0.is_empty() {
+            return write!(f, "/");
+        }
+        for idx in &self.0 {
+            write!(f, "/{}", idx)?;
+        }
+        Ok(())
+    }
+}
+
+/// A single edit of a [`TreeDiff`].
+#[derive(Debug, PartialEq)]
+pub enum TreeEdit<'a, T> {
+    /// The node and all its descendants were removed. They aren't reported separately.
+    Delete(&'a T),
+    /// The node was removed, but some of its descendants were kept. Its remaining children take
+    /// its place in its parent, and its removed descendants are reported separately.
+    Unwrap(&'a T),
+    /// The node and all its descendants were added. They aren't reported separately.
+    Insert(&'a T),
+    /// The node was added around existing nodes, which became its descendants. Its other added
+    /// descendants are reported separately.
+    Wrap(&'a T),
+    /// The node was kept, but its label changed.
+    Update(&'a T, &'a T),
+    /// The subtree was removed from one location and added unchanged at another.
+    Move { from: NodePath, node: &'a T },
+}
+
+/// A single change between two trees. Deleted nodes are located by their path in the left tree, and
+/// all other changes by their path in the right tree.
+#[derive(Debug, PartialEq)]
+pub struct TreeChange<'a, T> {
+    pub path: NodePath,
+    pub edit: TreeEdit<'a, T>,
+}
+
+/// A tree flattened into post-order, as the Zhang–Shasha algorithm works on.
+struct Flat<'a, T> {
+    nodes: Vec<&'a T>,
+    paths: Vec<NodePath>,
+    parents: Vec<Option<usize>>,
+    /// The leftmost leaf descendant of each node.
+    leftmost: Vec<usize>,
+    /// Nodes with no parent, or which have a left sibling, in post-order.
+    keyroots: Vec<usize>,
+}
+
+impl<'a, T: Tree> Flat<'a, T> {
+    fn new(root: &'a T) -> Flat<'a, T> {
+        let mut flat = Flat {
+            nodes: Vec::new(),
+            paths: Vec::new(),
+            parents: Vec::new(),
+            leftmost: Vec::new(),
+            keyroots: Vec::new(),
+        };
+        flat.visit(root, &mut Vec::new());
+        // The last node with each leftmost leaf is either the root or has a left sibling
+        let mut last = HashMap::new();
+        for (idx, &leaf) in flat.leftmost.iter().enumerate() {
+            last.insert(leaf, idx);
+        }
+        flat.keyroots = last.into_values().collect();
+        flat.keyroots.sort_unstable();
+        flat
+    }
+
+    fn visit(&mut self, node: &'a T, path: &mut Vec<usize>) -> usize {
+        let mut children = Vec::new();
+        for (idx, child) in node.children().iter().enumerate() {
+            path.push(idx);
+            children.push(self.visit(child, path));
+            path.pop();
+        }
+        let idx = self.nodes.len();
+        self.nodes.push(node);
+        self.paths.push(NodePath(path.clone()));
+        self.parents.push(None);
+        self.leftmost
+            .push(children.first().map_or(idx, |&first| self.leftmost[first]));
+        for child in children {
+            self.parents[child] = Some(idx);
+        }
+        idx
+    }
+
+    fn len(&self) -> usize {
+        self.nodes.len()
+    }
+}
+
+struct Distance<'f, 'a, T> {
+    l: &'f Flat<'a, T>,
+    r: &'f Flat<'a, T>,
+    /// The edit distance between each pair of subtrees.
+    tree: Vec<Vec<usize>>,
+}
+
+impl<T: Tree> Distance<'_, '_, T> {
+    fn update(&self, i: usize, j: usize) -> usize {
+        usize::from(self.l.nodes[i].label() != self.r.nodes[j].label())
+    }
+
+    /// Compute the distance between the forests ending at each node of the subtrees rooted at `i`
+    /// and `j`. Entry `[x][y]` covers left nodes `leftmost(i)..x` and right nodes `leftmost(j)..y`,
+    /// offset by one so the first row and column are the empty forests.
+    fn forest(&mut self, i: usize, j: usize) -> Vec<Vec<usize>> {
+        let (li, lj) = (self.l.leftmost[i], self.r.leftmost[j]);
+        let rows = i - li + 2;
+        let cols = j - lj + 2;
+        let mut fd = vec![vec![0; cols]; rows];
+        for x in 1..rows {
+            fd[x][0] = fd[x - 1][0] + 1;
+        }
+        for y in 1..cols {
+            fd[0][y] = fd[0][y - 1] + 1;
+        }
+        for x in 1..rows {
+            for y in 1..cols {
+                let (ni, nj) = (li + x - 1, lj + y - 1);
+                let (lx, ly) = (self.l.leftmost[ni], self.r.leftmost[nj]);
+                let edit = usize::min(fd[x - 1][y] + 1, fd[x][y - 1] + 1);
+                if lx == li && ly == lj {
+                    fd[x][y] = usize::min(edit, fd[x - 1][y - 1] + self.update(ni, nj));
+                    self.tree[ni][nj] = fd[x][y];
+                } else {
+                    let (px, py) = (lx - li, ly - lj);
+                    fd[x][y] = usize::min(edit, fd[px][py] + self.tree[ni][nj]);
+                }
+            }
+        }
+        fd
+    }
+}
+
+/// Find which left nodes are mapped to which right nodes by a minimal edit script.
+fn mapping<T: Tree>(l: &Flat<'_, T>, r: &Flat<'_, T>) -> Vec<(usize, usize)> {
+    let mut dist = Distance {
+        l,
+        r,
+        tree: vec![vec![0; r.len()]; l.len()],
+    };
+    for &i in &l.keyroots {
+        for &j in &r.keyroots {
+            dist.forest(i, j);
+        }
+    }
+
+    let mut out = Vec::new();
+    let mut stack = vec![(l.len() - 1, r.len() - 1)];
+    while let Some((i, j)) = stack.pop() {
+        let (li, lj) = (l.leftmost[i], r.leftmost[j]);
+        let fd = dist.forest(i, j);
+        let (mut x, mut y) = (i - li + 1, j - lj + 1);
+        while x > 0 && y > 0 {
+            let (ni, nj) = (li + x - 1, lj + y - 1);
+            let (lx, ly) = (l.leftmost[ni], r.leftmost[nj]);
+            if fd[x][y] == fd[x - 1][y] + 1 {
+                x -= 1;
+            } else if fd[x][y] == fd[x][y - 1] + 1 {
+                y -= 1;
+            } else if lx == li && ly == lj {
+                out.push((ni, nj));
+                x -= 1;
+                y -= 1;
+            } else {
+                stack.push((ni, nj));
+                x = lx - li;
+                y = ly - lj;
+            }
+        }
+    }
+    out
+}
+
+/// For each node, whether every node of the subtree rooted at it is marked.
+fn whole_subtrees<T>(flat: &Flat<'_, T>, marked: &[bool]) -> Vec<bool> {
+    // In post-order, descendants of a node are the nodes from its leftmost leaf up to itself
+    (0..flat.nodes.len())
+        .map(|idx| marked[flat.leftmost[idx]..=idx].iter().all(|&m| m))
+        .collect()
+}
+
+fn same_tree<T: Tree>(l: &T, r: &T) -> bool {
+    l.label() == r.label()
+        && l.children().len() == r.children().len()
+        && l.children()
+            .iter()
+            .zip(r.children())
+            .all(|(l, r)| same_tree(l, r))
+}
+
+impl<T: Tree> DiffAlgo<T> for TreeDiff {
+    type Diff<'a> = Vec<TreeChange<'a, T>>
+    where
+        T: 'a;
+
+    fn diff<'a>(l: &'a T, r: &'a T) -> Self::Diff<'a> {
+        let lf = Flat::new(l);
+        let rf = Flat::new(r);
+        let pairs = mapping(&lf, &rf);
+
+        let mut deleted = vec![true; lf.len()];
+        let mut inserted = vec![true; rf.len()];
+        for &(i, j) in &pairs {
+            deleted[i] = false;
+            inserted[j] = false;
+        }
+        let l_whole = whole_subtrees(&lf, &deleted);
+        let r_whole = whole_subtrees(&rf, &inserted);
+        // Only the topmost node of a wholly removed or added subtree is reported
+        let l_top = |i: usize| deleted[i] && !lf.parents[i].is_some_and(|p| l_whole[p]);
+        let r_top = |j: usize| inserted[j] && !rf.parents[j].is_some_and(|p| r_whole[p]);
+
+        let mut moved_from = vec![None; rf.len()];
+        let mut moved = vec![false; lf.len()];
+        for i in (0..lf.len()).rev().filter(|&i| l_top(i) && l_whole[i]) {
+            let found = (0..rf.len()).rev().find(|&j| {
+                r_top(j)
+                    && r_whole[j]
+                    && moved_from[j].is_none()
+                    && same_tree(lf.nodes[i], rf.nodes[j])
+            });
+            if let Some(j) = found {
+                moved_from[j] = Some(i);
+                moved[i] = true;
+            }
+        }
+
+        let mut out = (0..lf.len())
+            .filter(|&i| l_top(i) && !moved[i])
+            .map(|i| TreeChange {
+                path: lf.paths[i].clone(),
+                edit: if l_whole[i] {
+                    TreeEdit::Delete(lf.nodes[i])
+                } else {
+                    TreeEdit::Unwrap(lf.nodes[i])
+                },
+            })
+            .collect::<Vec<_>>();
+        out.sort_by(|l, r| l.path.cmp(&r.path));
+
+        let updates = pairs
+            .iter()
+            .filter(|&&(i, j)| lf.nodes[i].label() != rf.nodes[j].label())
+            .map(|&(i, j)| (j, TreeEdit::Update(lf.nodes[i], rf.nodes[j])));
+        let mut changes = (0..rf.len())
+            .filter(|&j| r_top(j))
+            .map(|j| match moved_from[j] {
+                Some(i) => (
+                    j,
+                    TreeEdit::Move {
+                        from: lf.paths[i].clone(),
+                        node: rf.nodes[j],
+                    },
+                ),
+                None if r_whole[j] => (j, TreeEdit::Insert(rf.nodes[j])),
+                None => (j, TreeEdit::Wrap(rf.nodes[j])),
+            })
+            .chain(updates)
+            .collect::<Vec<_>>();
+        changes.sort_by(|(l, _), (r, _)| rf.paths[*l].cmp(&rf.paths[*r]));
+        out.extend(changes.into_iter().map(|(j, edit)| TreeChange {
+            path: rf.paths[j].clone(),
+            edit,
+        }));
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Diffable;
+
+    #[derive(Debug, PartialEq)]
+    struct Node(&'static str, Vec<Node>);
+
+    impl Tree for Node {
+        type Label<'a> = &'a str;
+
+        fn label(&self) -> Self::Label<'_> {
+            self.0
+        }
+
+        fn children(&self) -> &[Self] {
+            &self.1
+        }
+    }
+
+    impl Diffable for Node {
+        type Diff<'a, A: DiffAlgo<Self::Item>> = A::Diff<'a>;
+        type Item = Node;
+
+        fn diff<'a, A: DiffAlgo<Self::Item>>(&'a self, other: &'a Self) -> A::Diff<'a> {
+            A::diff(self, other)
+        }
+    }
+
+    fn leaf(label: &'static str) -> Node {
+        Node(label, Vec::new())
+    }
+
+    #[test]
+    fn test_tree() {
+        let a = Node(
+            "fn",
+            vec![
+                Node("args", vec![leaf("a"), leaf("b")]),
+                Node("body", vec![leaf("let"), leaf("return")]),
+            ],
+        );
+        let b = Node(
+            "fn",
+            vec![
+                Node("args", vec![leaf("a"), leaf("c")]),
+                Node("body", vec![leaf("let"), leaf("if"), leaf("return")]),
+            ],
+        );
+        let d = a.diff::<TreeDiff>(&b);
+
+        assert_eq!(
+            d,
+            vec![
+                TreeChange {
+                    path: NodePath(vec![0, 1]),
+                    edit: TreeEdit::Update(&a.1[0].1[1], &b.1[0].1[1]),
+                },
+                TreeChange {
+                    path: NodePath(vec![1, 1]),
+                    edit: TreeEdit::Insert(&b.1[1].1[1]),
+                },
+            ],
+        );
+    }
+
+    #[test]
+    fn test_tree_move() {
+        let a = Node(
+            "root",
+            vec![
+                Node("moved", vec![leaf("1"), leaf("2")]),
+                leaf("x"),
+                Node("y", vec![leaf("gone")]),
+            ],
+        );
+        let b = Node(
+            "root",
+            vec![
+                leaf("x"),
+                leaf("y"),
+                Node("moved", vec![leaf("1"), leaf("2")]),
+            ],
+        );
+        let d = a.diff::<TreeDiff>(&b);
+
+        // Keeping the larger subtree in place is cheaper, so the smaller one is moved around it
+        assert_eq!(
+            d,
+            vec![
+                TreeChange {
+                    path: NodePath(vec![2]),
+                    edit: TreeEdit::Delete(&a.1[2]),
+                },
+                TreeChange {
+                    path: NodePath(vec![0]),
+                    edit: TreeEdit::Move {
+                        from: NodePath(vec![1]),
+                        node: &b.1[0],
+                    },
+                },
+                TreeChange {
+                    path: NodePath(vec![1]),
+                    edit: TreeEdit::Insert(&b.1[1]),
+                },
+            ],
+        );
+        assert_eq!(NodePath(vec![2, 0]).to_string(), "/2/0");
+    }
+
+    #[test]
+    fn test_tree_wrap() {
+        let a = Node("root", vec![Node("block", vec![leaf("a"), leaf("b")])]);
+        let b = Node(
+            "root",
+            vec![leaf("a"), Node("if", vec![leaf("b"), leaf("c")])],
+        );
+        let d = a.diff::<TreeDiff>(&b);
+
+        assert_eq!(
+            d,
+            vec![
+                TreeChange {
+                    path: NodePath(vec![0]),
+                    edit: TreeEdit::Unwrap(&a.1[0]),
+                },
+                TreeChange {
+                    path: NodePath(vec![1]),
+                    edit: TreeEdit::Wrap(&b.1[1]),
+                },
+                TreeChange {
+                    path: NodePath(vec![1, 1]),
+                    edit: TreeEdit::Insert(&b.1[1].1[1]),
+                },
+            ],
+        );
+    }
+}
@@ -0,0 +1,673 @@
+//! Structural differences of any [`Serialize`] type, without requiring a [`Diffable`]
+//! implementation. Values are serialized into a [`Value`] tree, and the trees are compared to
+//! produce a list of [`Change`]s, each addressed by the [`Path`] to the changed value.
+
+use crate::algo::DiffAlgo;
+use crate::{algo, DiffRes, Diffable};
+use serde::ser::{self, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::Hash;
+
+/// The location of a value within a [`Value`] tree, displayed like `.config.servers[2].port`.
+pub use crate::path::DiffPath as Path;
+pub use crate::path::Segment;
+
+/// A serialized value, as produced by [`to_value`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    /// `()`, or a unit struct.
+    Unit,
+    Bool(bool),
+    /// Any signed integer up to 64 bits.
+    I64(i64),
+    /// Any unsigned integer up to 64 bits.
+    U64(u64),
+    I128(i128),
+    U128(u128),
+    /// Either float type.
+    F64(f64),
+    Char(char),
+    Str(String),
+    Bytes(Vec<u8>),
+    Option(Option<Box<Value>>),
+    /// A sequence, tuple or tuple struct.
+    Seq(Vec<Value>),
+    /// A map, with its entries in serialization order.
+    Map(Vec<(Value, Value)>),
+    /// A struct, with its fields in serialization order.
+    Struct(Vec<(&'static str, Value)>),
+    /// An enum variant, holding its contents. Unit variants hold [`Value::Unit`].
+    Variant(&'static str, Box<Value>),
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fn list<T>(
+            f: &mut fmt::Formatter<'_>,
+            open: &str,
+            close: &str,
+            items: &[T],
+            mut item: impl FnMut(&mut fmt::Formatter<'_>, &T) -> fmt::Result,
+        ) -> fmt::Result {
+            write!(f, "{}", open)?;
+            for (idx, i) in items.iter().enumerate() {
+                if idx != 0 {
+                    write!(f, ", ")?;
+                }
+                item(f, i)?;
+            }
+            write!(f, "{}", close)
+        }
+
+        match self {
+            Value::Unit => write!(f, "()"),
+            Value::Bool(b) => write!(f, "{}", b),
+            Value::I64(i) => write!(f, "{}", i),
+            Value::U64(u) => write!(f, "{}", u),
+            Value::I128(i) => write!(f, "{}", i),
+            Value::U128(u) => write!(f, "{}", u),
+            Value::F64(v) => write!(f, "{:?}", v),
+            Value::Char(c) => write!(f, "{:?}", c),
+            Value::Str(s) => write!(f, "{:?}", s),
+            Value::Bytes(b) => write!(f, "{:02X?}", b),
+            Value::Option(None) => write!(f, "None"),
+            Value::Option(Some(v)) => write!(f, "Some({})", v),
+            Value::Seq(items) => list(f, "[", "]", items, |f, v| write!(f, "{}", v)),
+            Value::Map(items) => list(f, "{", "}", items, |f, (k, v)| write!(f, "{}: {}", k, v)),
+            Value::Struct(items) => list(f, "{", "}", items, |f, (k, v)| write!(f, "{}: {}", k, v)),
+            Value::Variant(name, v) if **v == Value::Unit => write!(f, "{}", name),
+            Value::Variant(name, v) => write!(f, "{}({})", name, v),
+        }
+    }
+}
+
+/// A single difference between two [`Value`] trees. [`DiffRes::Left`] is a removed value,
+/// [`DiffRes::Right`] an added value, and [`DiffRes::Both`] a value that was replaced.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Change {
+    pub path: Path,
+    pub res: DiffRes<Value>,
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.res {
+            DiffRes::Left(l) => write!(f, "-{}: {}", self.path, l),
+            DiffRes::Both(l, r) => write!(f, " {}: {} -> {}", self.path, l, r),
+            DiffRes::Right(r) => write!(f, "+{}: {}", self.path, r),
+        }
+    }
+}
+
+/// An error while serializing a value into a [`Value`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Error(String);
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl ser::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Error(msg.to_string())
+    }
+}
+
+/// Serialize a value into a [`Value`] tree.
+pub fn to_value<T: Serialize + ?Sized>(value: &T) -> Result<Value, Error> {
+    value.serialize(ValueSerializer)
+}
+
+/// Diff the entries of two maps or structs, matching up entries with equal keys. Keys are looked up
+/// by their `index`, and located by their `segment`.
+fn diff_entries<K: PartialEq, I: Eq + Hash>(
+    path: &Path,
+    l: &[(K, Value)],
+    r: &[(K, Value)],
+    index: impl Fn(&K) -> I,
+    segment: impl Fn(&K) -> Segment,
+    out: &mut Vec<Change>,
+) {
+    let mut by_key: HashMap<I, Vec<usize>> = HashMap::new();
+    for (idx, (key, _)) in r.iter().enumerate() {
+        by_key.entry(index(key)).or_default().push(idx);
+    }
+
+    let mut matched = vec![false; r.len()];
+    for (key, lv) in l {
+        let path = path.with(segment(key));
+        let found = by_key
+            .get(&index(key))
+            .and_then(|idxs| idxs.iter().copied().find(|&idx| r[idx].0 == *key));
+        match found {
+            Some(idx) => {
+                matched[idx] = true;
+                diff_into(&path, lv, &r[idx].1, out);
+            }
+            None => out.push(Change {
+                path,
+                res: DiffRes::Left(lv.clone()),
+            }),
+        }
+    }
+    for ((key, rv), matched) in r.iter().zip(matched) {
+        if !matched {
+            out.push(Change {
+                path: path.with(segment(key)),
+                res: DiffRes::Right(rv.clone()),
+            });
+        }
+    }
+}
+
+fn diff_into(path: &Path, l: &Value, r: &Value, out: &mut Vec<Change>) {
+    fn changed(path: &Path, l: &Value, r: &Value, out: &mut Vec<Change>) {
+        out.push(Change {
+            path: path.clone(),
+            res: DiffRes::Both(l.clone(), r.clone()),
+        })
+    }
+
+    match (l, r) {
+        (Value::Option(Some(l)), Value::Option(Some(r))) => diff_into(path, l, r, out),
+        (Value::Seq(l), Value::Seq(r)) => {
+            for idx in 0..usize::max(l.len(), r.len()) {
+                let path = path.with(Segment::Index(idx));
+                match (l.get(idx), r.get(idx)) {
+                    (Some(l), Some(r)) => diff_into(&path, l, r, out),
+                    (Some(l), None) => out.push(Change {
+                        path,
+                        res: DiffRes::Left(l.clone()),
+                    }),
+                    (None, Some(r)) => out.push(Change {
+                        path,
+                        res: DiffRes::Right(r.clone()),
+                    }),
+                    (None, None) => unreachable!(),
+                }
+            }
+        }
+        // Map keys may contain floats, so can't be hashed themselves
+        (Value::Map(l), Value::Map(r)) => diff_entries(
+            path,
+            l,
+            r,
+            Value::to_string,
+            |key| Segment::Key(key.to_string()),
+            out,
+        ),
+        (Value::Struct(l), Value::Struct(r)) => {
+            diff_entries(path, l, r, |name| *name, |name| Segment::Field(name), out)
+        }
+        (Value::Variant(ln, lv), Value::Variant(rn, rv)) if ln == rn => {
+            diff_into(path, lv, rv, out)
+        }
+        (l, r) if l != r => changed(path, l, r, out),
+        _ => (),
+    }
+}
+
+/// Generate a structural diff of two [`Value`] trees. Sequences are compared by position, and maps
+/// and structs by key. Only changed values are reported.
+pub fn diff_values(l: &Value, r: &Value) -> Vec<Change> {
+    let mut out = Vec::new();
+    diff_into(&Path::default(), l, r, &mut out);
+    out
+}
+
+/// Generate a diff of any [`Serialize`] type, by serializing both sides into a [`Value`] tree and
+/// comparing those. Fails if either side fails to serialize.
+pub struct StructuralDiff;
+
+impl<T: Serialize + ?Sized> DiffAlgo<T> for StructuralDiff {
+    type Diff<'a> = Result<Vec<Change>, Error>
+    where
+        T: 'a;
+
+    fn diff<'a>(l: &'a T, r: &'a T) -> Self::Diff<'a> {
+        Ok(diff_values(&to_value(l)?, &to_value(r)?))
+    }
+}
+
+impl DiffAlgo<Value> for algo::Default {
+    type Diff<'a> = Vec<Change>;
+
+    fn diff<'a>(l: &'a Value, r: &'a Value) -> Self::Diff<'a> {
+        diff_values(l, r)
+    }
+}
+
+impl Diffable for Value {
+    type Diff<'a, A: DiffAlgo<Self::Item>> = A::Diff<'a>;
+    type Item = Value;
+
+    fn diff<'a, A: DiffAlgo<Self::Item>>(&'a self, other: &'a Self) -> A::Diff<'a> {
+        A::diff(self, other)
+    }
+}
+
+struct ValueSerializer;
+
+struct SeqSerializer {
+    items: Vec<Value>,
+    variant: Option<&'static str>,
+}
+
+struct MapSerializer {
+    items: Vec<(Value, Value)>,
+    key: Option<Value>,
+}
+
+struct StructSerializer {
+    fields: Vec<(&'static str, Value)>,
+    variant: Option<&'static str>,
+}
+
+fn wrap(variant: Option<&'static str>, value: Value) -> Value {
+    match variant {
+        Some(name) => Value::Variant(name, Box::new(value)),
+        None => value,
+    }
+}
+
+impl ser::Serializer for ValueSerializer {
+    type Ok = Value;
+    type Error = Error;
+    type SerializeSeq = SeqSerializer;
+    type SerializeTuple = SeqSerializer;
+    type SerializeTupleStruct = SeqSerializer;
+    type SerializeTupleVariant = SeqSerializer;
+    type SerializeMap = MapSerializer;
+    type SerializeStruct = StructSerializer;
+    type SerializeStructVariant = StructSerializer;
+
+    fn serialize_bool(self, v: bool) -> Result<Value, Error> {
+        Ok(Value::Bool(v))
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<Value, Error> {
+        Ok(Value::I64(v.into()))
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<Value, Error> {
+        Ok(Value::I64(v.into()))
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<Value, Error> {
+        Ok(Value::I64(v.into()))
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<Value, Error> {
+        Ok(Value::I64(v))
+    }
+
+    fn serialize_i128(self, v: i128) -> Result<Value, Error> {
+        Ok(Value::I128(v))
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<Value, Error> {
+        Ok(Value::U64(v.into()))
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<Value, Error> {
+        Ok(Value::U64(v.into()))
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<Value, Error> {
+        Ok(Value::U64(v.into()))
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<Value, Error> {
+        Ok(Value::U64(v))
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<Value, Error> {
+        Ok(Value::U128(v))
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<Value, Error> {
+        Ok(Value::F64(v.into()))
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<Value, Error> {
+        Ok(Value::F64(v))
+    }
+
+    fn serialize_char(self, v: char) -> Result<Value, Error> {
+        Ok(Value::Char(v))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Value, Error> {
+        Ok(Value::Str(v.to_owned()))
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<Value, Error> {
+        Ok(Value::Bytes(v.to_owned()))
+    }
+
+    fn serialize_none(self) -> Result<Value, Error> {
+        Ok(Value::Option(None))
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<Value, Error> {
+        Ok(Value::Option(Some(Box::new(to_value(value)?))))
+    }
+
+    fn serialize_unit(self) -> Result<Value, Error> {
+        Ok(Value::Unit)
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Value, Error> {
+        Ok(Value::Unit)
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<Value, Error> {
+        Ok(Value::Variant(variant, Box::new(Value::Unit)))
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<Value, Error> {
+        to_value(value)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<Value, Error> {
+        Ok(Value::Variant(variant, Box::new(to_value(value)?)))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<SeqSerializer, Error> {
+        Ok(SeqSerializer {
+            items: Vec::with_capacity(len.unwrap_or(0)),
+            variant: None,
+        })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<SeqSerializer, Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<SeqSerializer, Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<SeqSerializer, Error> {
+        Ok(SeqSerializer {
+            items: Vec::with_capacity(len),
+            variant: Some(variant),
+        })
+    }
+
+    fn serialize_map(self, len: Option<usize>) -> Result<MapSerializer, Error> {
+        Ok(MapSerializer {
+            items: Vec::with_capacity(len.unwrap_or(0)),
+            key: None,
+        })
+    }
+
+    fn serialize_struct(self, _name: &'static str, len: usize) -> Result<StructSerializer, Error> {
+        Ok(StructSerializer {
+            fields: Vec::with_capacity(len),
+            variant: None,
+        })
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<StructSerializer, Error> {
+        Ok(StructSerializer {
+            fields: Vec::with_capacity(len),
+            variant: Some(variant),
+        })
+    }
+}
+
+impl SeqSerializer {
+    fn push<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.items.push(to_value(value)?);
+        Ok(())
+    }
+
+    fn finish(self) -> Result<Value, Error> {
+        Ok(wrap(self.variant, Value::Seq(self.items)))
+    }
+}
+
+impl ser::SerializeSeq for SeqSerializer {
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Value, Error> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTuple for SeqSerializer {
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Value, Error> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTupleStruct for SeqSerializer {
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Value, Error> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTupleVariant for SeqSerializer {
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Value, Error> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeMap for MapSerializer {
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), Error> {
+        self.key = Some(to_value(key)?);
+        Ok(())
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        let key = self
+            .key
+            .take()
+            .ok_or_else(|| Error(String::from("map value serialized before its key")))?;
+        self.items.push((key, to_value(value)?));
+        Ok(())
+    }
+
+    fn end(self) -> Result<Value, Error> {
+        Ok(Value::Map(self.items))
+    }
+}
+
+impl StructSerializer {
+    fn push<T: Serialize + ?Sized>(&mut self, key: &'static str, value: &T) -> Result<(), Error> {
+        self.fields.push((key, to_value(value)?));
+        Ok(())
+    }
+
+    fn finish(self) -> Result<Value, Error> {
+        Ok(wrap(self.variant, Value::Struct(self.fields)))
+    }
+}
+
+impl ser::SerializeStruct for StructSerializer {
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        self.push(key, value)
+    }
+
+    fn end(self) -> Result<Value, Error> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeStructVariant for StructSerializer {
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        self.push(key, value)
+    }
+
+    fn end(self) -> Result<Value, Error> {
+        self.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Serialize;
+    use std::collections::BTreeMap;
+
+    #[derive(Serialize)]
+    struct Server {
+        host: String,
+        port: u16,
+    }
+
+    #[derive(Serialize)]
+    struct Config {
+        name: String,
+        servers: Vec<Server>,
+        env: BTreeMap<String, String>,
+    }
+
+    #[derive(Serialize)]
+    struct Root {
+        config: Config,
+    }
+
+    fn server(host: &str, port: u16) -> Server {
+        Server {
+            host: host.to_owned(),
+            port,
+        }
+    }
+
+    #[test]
+    fn test_structural() {
+        let a = Root {
+            config: Config {
+                name: String::from("prod"),
+                servers: vec![server("a", 80), server("b", 80), server("c", 80)],
+                env: BTreeMap::from([(String::from("LOG"), String::from("info"))]),
+            },
+        };
+        let b = Root {
+            config: Config {
+                name: String::from("prod"),
+                servers: vec![server("a", 80), server("b", 80), server("c", 8080)],
+                env: BTreeMap::from([(String::from("MODE"), String::from("fast"))]),
+            },
+        };
+        let d = StructuralDiff::diff(&a, &b).unwrap();
+
+        let lines = d.iter().map(Change::to_string).collect::<Vec<_>>();
+        assert_eq!(
+            lines,
+            vec![
+                " .config.servers[2].port: 80 -> 8080",
+                "-.config.env[\"LOG\"]: \"info\"",
+                "+.config.env[\"MODE\"]: \"fast\"",
+            ],
+        );
+    }
+
+    #[test]
+    fn test_structural_variants() {
+        #[derive(Serialize)]
+        enum Shape {
+            Circle { r: f64 },
+            Square(f64),
+        }
+
+        let a = vec![Shape::Circle { r: 1.0 }, Shape::Square(2.0)];
+        let b = vec![Shape::Circle { r: 1.5 }];
+        let d = StructuralDiff::diff(&a, &b).unwrap();
+
+        assert_eq!(
+            d,
+            vec![
+                Change {
+                    path: Path(vec![Segment::Index(0), Segment::Field("r")]),
+                    res: DiffRes::Both(Value::F64(1.0), Value::F64(1.5)),
+                },
+                Change {
+                    path: Path(vec![Segment::Index(1)]),
+                    res: DiffRes::Left(Value::Variant("Square", Box::new(Value::F64(2.0)))),
+                },
+            ],
+        );
+    }
+}
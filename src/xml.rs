@@ -0,0 +1,510 @@
+//! Differences of XML documents. Elements are compared by their namespace-qualified names rather
+//! than the prefixes used to write them, attributes by name regardless of order, and children by
+//! their longest common subsequence, with unchanged children that moved reported as moves. Each
+//! change is located by an XPath-like path.
+
+use crate::algo::DiffAlgo;
+use crate::builtin::LcsDiff;
+use crate::{algo, DiffRes, Diffable};
+use std::fmt;
+
+/// Generate an element-aware diff of two XML documents. Only changed nodes are reported.
+pub struct XmlDiff;
+
+/// The name of an element or attribute. Names are equal if their namespace and local name are,
+/// regardless of the prefix used.
+#[derive(Debug, Clone)]
+pub struct Name {
+    pub namespace: Option<String>,
+    pub prefix: Option<String>,
+    pub local: String,
+}
+
+impl PartialEq for Name {
+    fn eq(&self, other: &Self) -> bool {
+        self.namespace == other.namespace && self.local == other.local
+    }
+}
+
+impl fmt::Display for Name {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.prefix {
+            Some(prefix) => write!(f, "{}:{}", prefix, self.local),
+            None => write!(f, "{}", self.local),
+        }
+    }
+}
+
+/// An XML element, alongside the line it starts on.
+#[derive(Debug, Clone)]
+pub struct Element {
+    pub name: Name,
+    pub attributes: Vec<(Name, String)>,
+    pub children: Vec<Node>,
+    /// The 1-indexed source line of this element.
+    pub line: usize,
+}
+
+impl Element {
+    fn attribute(&self, name: &Name) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+/// Elements are equal if they have the same name, attributes in any order, and children, regardless
+/// of where they appear in the source.
+impl PartialEq for Element {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name
+            && self.attributes.len() == other.attributes.len()
+            && self
+                .attributes
+                .iter()
+                .all(|(n, v)| other.attribute(n) == Some(v))
+            && self.children == other.children
+    }
+}
+
+/// A child of an element. Comments, processing instructions, and text consisting only of
+/// whitespace are not kept.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Node {
+    Element(Element),
+    Text(String),
+}
+
+fn escape(f: &mut fmt::Formatter<'_>, text: &str, quote: bool) -> fmt::Result {
+    for c in text.chars() {
+        match c {
+            '<' => write!(f, "&lt;")?,
+            '>' => write!(f, "&gt;")?,
+            '&' => write!(f, "&amp;")?,
+            '"' if quote => write!(f, "&quot;")?,
+            c => write!(f, "{}", c)?,
+        }
+    }
+    Ok(())
+}
+
+/// Elements are written as compact XML, using their original prefixes.
+impl fmt::Display for Element {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<{}", self.name)?;
+        for (name, val) in &self.attributes {
+            write!(f, " {}=\"", name)?;
+            escape(f, val, true)?;
+            write!(f, "\"")?;
+        }
+        if self.children.is_empty() {
+            return write!(f, "/>");
+        }
+        write!(f, ">")?;
+        for child in &self.children {
+            write!(f, "{}", child)?;
+        }
+        write!(f, "</{}>", self.name)
+    }
+}
+
+impl fmt::Display for Node {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Node::Element(elem) => write!(f, "{}", elem),
+            Node::Text(text) => escape(f, text, false),
+        }
+    }
+}
+
+/// A parsed XML document.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Document {
+    // Always an element, but kept as a node so a changed root can be reported like any other node
+    root: Node,
+}
+
+impl Document {
+    /// The root element of this document.
+    pub fn root(&self) -> &Element {
+        match &self.root {
+            Node::Element(elem) => elem,
+            Node::Text(_) => unreachable!("document root is always an element"),
+        }
+    }
+}
+
+/// An error from parsing an XML document.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Error {
+    pub line: usize,
+    pub col: usize,
+    pub msg: String,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.line, self.col, self.msg)
+    }
+}
+
+impl std::error::Error for Error {}
+
+fn convert(doc: &roxmltree::Document<'_>, node: roxmltree::Node<'_, '_>) -> Element {
+    let name = |namespace: Option<&str>, local: &str| Name {
+        namespace: namespace.map(str::to_owned),
+        prefix: namespace
+            .and_then(|ns| node.lookup_prefix(ns))
+            .map(str::to_owned),
+        local: local.to_owned(),
+    };
+    let tag = node.tag_name();
+    Element {
+        name: name(tag.namespace(), tag.name()),
+        attributes: node
+            .attributes()
+            .map(|attr| (name(attr.namespace(), attr.name()), attr.value().to_owned()))
+            .collect(),
+        children: node
+            .children()
+            .filter_map(|child| match child.node_type() {
+                roxmltree::NodeType::Element => Some(Node::Element(convert(doc, child))),
+                roxmltree::NodeType::Text => child
+                    .text()
+                    .filter(|text| !text.trim().is_empty())
+                    .map(|text| Node::Text(text.to_owned())),
+                _ => None,
+            })
+            .collect(),
+        line: doc.text_pos_at(node.range().start).row as usize,
+    }
+}
+
+/// Parse an XML document.
+pub fn parse(src: &str) -> Result<Document, Error> {
+    let doc = roxmltree::Document::parse(src).map_err(|err| Error {
+        line: err.pos().row as usize,
+        col: err.pos().col as usize,
+        msg: err.to_string(),
+    })?;
+    Ok(Document {
+        root: Node::Element(convert(&doc, doc.root_element())),
+    })
+}
+
+/// A single edit between two XML documents.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Edit<'a> {
+    /// An attribute of the element at the path was removed, added, or changed.
+    Attribute {
+        name: &'a Name,
+        res: DiffRes<&'a str>,
+    },
+    /// The text node at the path was changed.
+    Text(&'a str, &'a str),
+    /// The node at the path was removed, added, or replaced by a different kind of node.
+    Node(DiffRes<&'a Node>),
+    /// The node at the path is unchanged, but was moved from another location.
+    Moved { from: String, node: &'a Node },
+}
+
+/// A single difference between two XML documents. Removed nodes are located by their path in the
+/// left document, and all other changes by their path in the right document.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Change<'a> {
+    pub path: String,
+    pub edit: Edit<'a>,
+}
+
+impl fmt::Display for Change<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.edit {
+            Edit::Attribute { name, res } => match res {
+                DiffRes::Left(l) => write!(f, "-{}/@{}: {:?}", self.path, name, l),
+                DiffRes::Both(l, r) => write!(f, " {}/@{}: {:?} -> {:?}", self.path, name, l, r),
+                DiffRes::Right(r) => write!(f, "+{}/@{}: {:?}", self.path, name, r),
+            },
+            Edit::Text(l, r) => write!(f, " {}: {:?} -> {:?}", self.path, l, r),
+            Edit::Node(res) => match res {
+                DiffRes::Left(l) => write!(f, "-{}: {}", self.path, l),
+                DiffRes::Both(l, r) => write!(f, " {}: {} -> {}", self.path, l, r),
+                DiffRes::Right(r) => write!(f, "+{}: {}", self.path, r),
+            },
+            Edit::Moved { from, node } => write!(f, "~{}: {} (from {})", self.path, node, from),
+        }
+    }
+}
+
+/// The path of a child, using its position among siblings of the same name as XPath does.
+fn child_path(path: &str, children: &[Node], idx: usize) -> String {
+    let same = |node: &Node| match (node, &children[idx]) {
+        (Node::Element(l), Node::Element(r)) => l.name == r.name,
+        (Node::Text(_), Node::Text(_)) => true,
+        _ => false,
+    };
+    let pos = children[..idx].iter().filter(|n| same(n)).count() + 1;
+    match &children[idx] {
+        Node::Element(elem) => format!("{}/{}[{}]", path, elem.name, pos),
+        Node::Text(_) => format!("{}/text()[{}]", path, pos),
+    }
+}
+
+fn diff_node<'a>(l_path: &str, r_path: &str, l: &'a Node, r: &'a Node, out: &mut Vec<Change<'a>>) {
+    match (l, r) {
+        (Node::Element(le), Node::Element(re)) if le.name == re.name => {
+            diff_element(l_path, r_path, le, re, out)
+        }
+        (Node::Text(lt), Node::Text(rt)) if lt != rt => out.push(Change {
+            path: r_path.to_owned(),
+            edit: Edit::Text(lt, rt),
+        }),
+        (Node::Text(_), Node::Text(_)) => (),
+        _ => out.push(Change {
+            path: r_path.to_owned(),
+            edit: Edit::Node(DiffRes::Both(l, r)),
+        }),
+    }
+}
+
+fn diff_element<'a>(
+    l_path: &str,
+    r_path: &str,
+    l: &'a Element,
+    r: &'a Element,
+    out: &mut Vec<Change<'a>>,
+) {
+    for (name, lv) in &l.attributes {
+        let res = match r.attribute(name) {
+            Some(rv) if rv == lv => continue,
+            Some(rv) => DiffRes::Both(lv.as_str(), rv),
+            None => DiffRes::Left(lv.as_str()),
+        };
+        let path = if let DiffRes::Left(_) = res {
+            l_path
+        } else {
+            r_path
+        };
+        out.push(Change {
+            path: path.to_owned(),
+            edit: Edit::Attribute { name, res },
+        });
+    }
+    for (name, rv) in &r.attributes {
+        if l.attribute(name).is_none() {
+            out.push(Change {
+                path: r_path.to_owned(),
+                edit: Edit::Attribute {
+                    name,
+                    res: DiffRes::Right(rv),
+                },
+            });
+        }
+    }
+    diff_children(l_path, r_path, &l.children, &r.children, out);
+}
+
+fn diff_children<'a>(
+    l_path: &str,
+    r_path: &str,
+    l: &'a [Node],
+    r: &'a [Node],
+    out: &mut Vec<Change<'a>>,
+) {
+    // Indices of each run of removed children, and the run of added children directly after it
+    let mut runs: Vec<(Vec<usize>, Vec<usize>)> = Vec::new();
+    let mut l_idx = 0;
+    let mut r_idx = 0;
+    let mut prev_left = false;
+    for chunk in LcsDiff::diff(l, r) {
+        match chunk {
+            DiffRes::Both(both, _) => {
+                l_idx += both.len();
+                r_idx += both.len();
+                prev_left = false;
+            }
+            DiffRes::Left(removed) => {
+                runs.push(((l_idx..l_idx + removed.len()).collect(), Vec::new()));
+                l_idx += removed.len();
+                prev_left = true;
+            }
+            DiffRes::Right(added) => {
+                let indices = (r_idx..r_idx + added.len()).collect();
+                match runs.last_mut() {
+                    Some((_, run)) if prev_left => *run = indices,
+                    _ => runs.push((Vec::new(), indices)),
+                }
+                r_idx += added.len();
+                prev_left = false;
+            }
+        }
+    }
+
+    // A removed child equal to an added one anywhere else was moved
+    for run in 0..runs.len() {
+        let mut idx = 0;
+        while idx < runs[run].0.len() {
+            let li = runs[run].0[idx];
+            let found = runs.iter().enumerate().find_map(|(other, (_, added))| {
+                added
+                    .iter()
+                    .position(|&ri| l[li] == r[ri])
+                    .map(|pos| (other, pos))
+            });
+            match found {
+                Some((other, pos)) => {
+                    let ri = runs[other].1.remove(pos);
+                    runs[run].0.remove(idx);
+                    out.push(Change {
+                        path: child_path(r_path, r, ri),
+                        edit: Edit::Moved {
+                            from: child_path(l_path, l, li),
+                            node: &r[ri],
+                        },
+                    });
+                }
+                None => idx += 1,
+            }
+        }
+    }
+
+    // Remaining children of the same kind and name were changed, preferring ones in the same run,
+    // which stayed in place. Otherwise the child was also moved.
+    let same = |li: usize, ri: usize| match (&l[li], &r[ri]) {
+        (Node::Element(le), Node::Element(re)) => le.name == re.name,
+        (Node::Text(_), Node::Text(_)) => true,
+        _ => false,
+    };
+    let mut taken = vec![false; r.len()];
+    for (run, (removed, added)) in runs.iter().enumerate() {
+        for &li in removed {
+            let free = |ri: &&usize| !taken[**ri] && same(li, **ri);
+            let found = added.iter().find(free).map(|&ri| (ri, false)).or_else(|| {
+                runs.iter()
+                    .enumerate()
+                    .filter(|&(other, _)| other != run)
+                    .find_map(|(_, (_, added))| added.iter().find(free))
+                    .map(|&ri| (ri, true))
+            });
+            let l_child = child_path(l_path, l, li);
+            match found {
+                Some((ri, moved)) => {
+                    taken[ri] = true;
+                    let r_child = child_path(r_path, r, ri);
+                    if moved {
+                        out.push(Change {
+                            path: r_child.clone(),
+                            edit: Edit::Moved {
+                                from: l_child.clone(),
+                                node: &r[ri],
+                            },
+                        });
+                    }
+                    diff_node(&l_child, &r_child, &l[li], &r[ri], out);
+                }
+                None => out.push(Change {
+                    path: l_child,
+                    edit: Edit::Node(DiffRes::Left(&l[li])),
+                }),
+            }
+        }
+    }
+    for (_, added) in &runs {
+        for &ri in added {
+            if !taken[ri] {
+                out.push(Change {
+                    path: child_path(r_path, r, ri),
+                    edit: Edit::Node(DiffRes::Right(&r[ri])),
+                });
+            }
+        }
+    }
+}
+
+impl DiffAlgo<Document> for XmlDiff {
+    type Diff<'a> = Vec<Change<'a>>;
+
+    fn diff<'a>(l: &'a Document, r: &'a Document) -> Self::Diff<'a> {
+        let mut out = Vec::new();
+        let l_path = format!("/{}", l.root().name);
+        let r_path = format!("/{}", r.root().name);
+        diff_node(&l_path, &r_path, &l.root, &r.root, &mut out);
+        out
+    }
+}
+
+impl DiffAlgo<Document> for algo::Default {
+    type Diff<'a> = Vec<Change<'a>>;
+
+    fn diff<'a>(l: &'a Document, r: &'a Document) -> Self::Diff<'a> {
+        XmlDiff::diff(l, r)
+    }
+}
+
+impl Diffable for Document {
+    type Diff<'a, A: DiffAlgo<Self::Item>> = A::Diff<'a>;
+    type Item = Document;
+
+    fn diff<'a, A: DiffAlgo<Self::Item>>(&'a self, other: &'a Self) -> A::Diff<'a> {
+        A::diff(self, other)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_xml() {
+        let a = parse(
+            r#"<project xmlns="http://maven.apache.org/POM/4.0.0">
+  <version>1.0</version>
+  <dependencies>
+    <dependency scope="test" optional="true"><artifactId>junit</artifactId></dependency>
+    <dependency><artifactId>guava</artifactId></dependency>
+    <dependency><artifactId>slf4j</artifactId></dependency>
+  </dependencies>
+  <description>Old &amp; busted</description>
+</project>"#,
+        )
+        .unwrap();
+        let b = parse(
+            r#"<pom:project xmlns:pom="http://maven.apache.org/POM/4.0.0">
+  <pom:version>1.1</pom:version>
+  <pom:dependencies>
+    <pom:dependency><pom:artifactId>slf4j</pom:artifactId></pom:dependency>
+    <pom:dependency optional="true" scope="compile"><pom:artifactId>junit</pom:artifactId></pom:dependency>
+    <pom:dependency><pom:artifactId>guava</pom:artifactId></pom:dependency>
+    <pom:dependency><pom:artifactId>serde</pom:artifactId></pom:dependency>
+  </pom:dependencies>
+</pom:project>"#,
+        )
+        .unwrap();
+        let d = crate::diff(&a, &b);
+
+        let lines = d.iter().map(Change::to_string).collect::<Vec<_>>();
+        assert_eq!(
+            lines,
+            vec![
+                " /pom:project/pom:version[1]/text()[1]: \"1.0\" -> \"1.1\"",
+                "~/pom:project/pom:dependencies[1]/pom:dependency[3]: \
+                 <pom:dependency><pom:artifactId>guava</pom:artifactId></pom:dependency> \
+                 (from /project/dependencies[1]/dependency[2])",
+                "~/pom:project/pom:dependencies[1]/pom:dependency[2]: \
+                 <pom:dependency optional=\"true\" scope=\"compile\">\
+                 <pom:artifactId>junit</pom:artifactId></pom:dependency> \
+                 (from /project/dependencies[1]/dependency[1])",
+                " /pom:project/pom:dependencies[1]/pom:dependency[2]/@scope: \"test\" -> \"compile\"",
+                "+/pom:project/pom:dependencies[1]/pom:dependency[4]: \
+                 <pom:dependency><pom:artifactId>serde</pom:artifactId></pom:dependency>",
+                "-/project/description[1]: <description>Old &amp; busted</description>",
+            ],
+        );
+    }
+
+    #[test]
+    fn test_xml_error() {
+        let err = parse("<a>\n<b></a>").unwrap_err();
+        assert_eq!(err.line, 2);
+    }
+}
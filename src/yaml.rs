@@ -0,0 +1,419 @@
+//! Differences of YAML documents. Both sides are parsed, including multi-document streams, and
+//! compared structurally: mappings by key regardless of order, and sequences by their longest
+//! common subsequence. Each change is located by a path and the source line of the nodes involved.
+
+use crate::algo::DiffAlgo;
+use crate::builtin::diff_paired;
+use crate::{algo, DiffRes, Diffable};
+use std::collections::HashMap;
+use std::fmt;
+use yaml_rust2::parser::{Event, MarkedEventReceiver, Parser};
+use yaml_rust2::scanner::{Marker, TScalarStyle};
+use yaml_rust2::Yaml;
+
+/// Generate a structural diff of two YAML streams. Only changed values are reported.
+pub struct YamlDiff;
+
+/// A parsed YAML node, alongside the line it starts on.
+#[derive(Debug, Clone)]
+pub struct Node {
+    /// The 1-indexed source line of this node.
+    pub line: usize,
+    pub value: Value,
+}
+
+/// The value of a YAML node. Aliases are resolved to a copy of the node they refer to.
+#[derive(Debug, Clone)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Int(i64),
+    /// A floating point number, kept as written.
+    Real(String),
+    Str(String),
+    Seq(Vec<Node>),
+    Map(Vec<(Node, Node)>),
+}
+
+impl Value {
+    fn get(&self, key: &Node) -> Option<&Node> {
+        match self {
+            Value::Map(entries) => entries.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+}
+
+/// Nodes are equal if their values are, regardless of where they appear in the source.
+impl PartialEq for Node {
+    fn eq(&self, other: &Self) -> bool {
+        self.value == other.value
+    }
+}
+
+/// Mappings are equal if they have the same entries, in any order.
+impl PartialEq for Value {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Value::Null, Value::Null) => true,
+            (Value::Bool(l), Value::Bool(r)) => l == r,
+            (Value::Int(l), Value::Int(r)) => l == r,
+            (Value::Real(l), Value::Real(r)) => l == r,
+            (Value::Str(l), Value::Str(r)) => l == r,
+            (Value::Seq(l), Value::Seq(r)) => l == r,
+            (Value::Map(l), Value::Map(r)) => {
+                l.len() == r.len() && l.iter().all(|(k, v)| other.get(k) == Some(v))
+            }
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for Node {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.value {
+            Value::Null => write!(f, "null"),
+            Value::Bool(b) => write!(f, "{}", b),
+            Value::Int(i) => write!(f, "{}", i),
+            Value::Real(r) => write!(f, "{}", r),
+            Value::Str(s) => write!(f, "{:?}", s),
+            Value::Seq(items) => {
+                write!(f, "[")?;
+                for (idx, item) in items.iter().enumerate() {
+                    if idx != 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", item)?;
+                }
+                write!(f, "]")
+            }
+            Value::Map(entries) => {
+                write!(f, "{{")?;
+                for (idx, (key, val)) in entries.iter().enumerate() {
+                    if idx != 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}: {}", key, val)?;
+                }
+                write!(f, "}}")
+            }
+        }
+    }
+}
+
+/// A parsed YAML stream, containing one node per document.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Stream(pub Vec<Node>);
+
+/// An error from parsing a YAML stream.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Error {
+    pub line: usize,
+    pub col: usize,
+    pub msg: String,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.line, self.col, self.msg)
+    }
+}
+
+impl std::error::Error for Error {}
+
+#[derive(Default)]
+struct Builder {
+    docs: Vec<Node>,
+    // Open collections, with the anchor they should be registered under once complete
+    stack: Vec<(Node, usize)>,
+    // A mapping key waiting for its value, for each open mapping
+    keys: Vec<Option<Node>>,
+    anchors: HashMap<usize, Node>,
+}
+
+impl Builder {
+    fn push(&mut self, node: Node, anchor: usize) {
+        if anchor != 0 {
+            self.anchors.insert(anchor, node.clone());
+        }
+        match self.stack.last_mut().map(|(parent, _)| &mut parent.value) {
+            None => self.docs.push(node),
+            Some(Value::Seq(items)) => items.push(node),
+            Some(Value::Map(entries)) => {
+                let key = self.keys.last_mut().expect("mapping without key slot");
+                match key.take() {
+                    Some(key) => entries.push((key, node)),
+                    None => *key = Some(node),
+                }
+            }
+            Some(_) => unreachable!("only collections are pushed to the stack"),
+        }
+    }
+}
+
+impl MarkedEventReceiver for Builder {
+    fn on_event(&mut self, ev: Event, mark: Marker) {
+        let line = mark.line();
+        match ev {
+            Event::Scalar(val, style, anchor, tag) => {
+                // Only plain scalars without an explicit string tag are resolved to other types
+                let value =
+                    if style != TScalarStyle::Plain || tag.is_some_and(|tag| tag.suffix == "str") {
+                        Value::Str(val)
+                    } else {
+                        match Yaml::from_str(&val) {
+                            Yaml::Null => Value::Null,
+                            Yaml::Boolean(b) => Value::Bool(b),
+                            Yaml::Integer(i) => Value::Int(i),
+                            Yaml::Real(r) => Value::Real(r),
+                            _ => Value::Str(val),
+                        }
+                    };
+                self.push(Node { line, value }, anchor);
+            }
+            Event::SequenceStart(anchor, _) => {
+                let node = Node {
+                    line,
+                    value: Value::Seq(Vec::new()),
+                };
+                self.stack.push((node, anchor));
+            }
+            Event::MappingStart(anchor, _) => {
+                let node = Node {
+                    line,
+                    value: Value::Map(Vec::new()),
+                };
+                self.stack.push((node, anchor));
+                self.keys.push(None);
+            }
+            Event::SequenceEnd | Event::MappingEnd => {
+                if matches!(ev, Event::MappingEnd) {
+                    self.keys.pop();
+                }
+                let (node, anchor) = self.stack.pop().expect("unbalanced collection end");
+                self.push(node, anchor);
+            }
+            Event::Alias(id) => {
+                let node = self.anchors.get(&id).cloned().unwrap_or(Node {
+                    line,
+                    value: Value::Null,
+                });
+                self.push(Node { line, ..node }, 0);
+            }
+            _ => (),
+        }
+    }
+}
+
+/// Parse a YAML stream, which may contain multiple documents.
+pub fn parse(src: &str) -> Result<Stream, Error> {
+    let mut builder = Builder::default();
+    Parser::new_from_str(src)
+        .load(&mut builder, true)
+        .map_err(|err| Error {
+            line: err.marker().line(),
+            col: err.marker().col(),
+            msg: err.info().to_owned(),
+        })?;
+    Ok(Stream(builder.docs))
+}
+
+/// A single difference between two YAML streams. [`DiffRes::Left`] is a removed node, located by
+/// its document index and path in the left stream. [`DiffRes::Right`] is an added node, and
+/// [`DiffRes::Both`] a replaced node, both located by their position in the right stream.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Change<'a> {
+    pub document: usize,
+    pub path: String,
+    pub res: DiffRes<&'a Node>,
+}
+
+impl fmt::Display for Change<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let path = if self.path.is_empty() {
+            "."
+        } else {
+            &self.path
+        };
+        match &self.res {
+            DiffRes::Left(l) => write!(f, "-{}:{} (line {}): {}", self.document, path, l.line, l),
+            DiffRes::Both(l, r) => write!(
+                f,
+                " {}:{} (line {} -> {}): {} -> {}",
+                self.document, path, l.line, r.line, l, r
+            ),
+            DiffRes::Right(r) => write!(f, "+{}:{} (line {}): {}", self.document, path, r.line, r),
+        }
+    }
+}
+
+fn key_path(path: &str, key: &Node) -> String {
+    match &key.value {
+        Value::Str(s) => format!("{}.{}", path, s),
+        _ => format!("{}[{}]", path, key),
+    }
+}
+
+fn diff_into<'a>(document: usize, path: &str, l: &'a Node, r: &'a Node, out: &mut Vec<Change<'a>>) {
+    match (&l.value, &r.value) {
+        (Value::Map(lm), Value::Map(rm)) => {
+            for (key, lv) in lm {
+                let path = key_path(path, key);
+                match r.value.get(key) {
+                    Some(rv) => diff_into(document, &path, lv, rv, out),
+                    None => out.push(Change {
+                        document,
+                        path,
+                        res: DiffRes::Left(lv),
+                    }),
+                }
+            }
+            for (key, rv) in rm {
+                if l.value.get(key).is_none() {
+                    out.push(Change {
+                        document,
+                        path: key_path(path, key),
+                        res: DiffRes::Right(rv),
+                    });
+                }
+            }
+        }
+        (Value::Seq(ls), Value::Seq(rs)) => {
+            for (idx, res) in diff_paired(ls, rs) {
+                let path = format!("{}[{}]", path, idx);
+                match res {
+                    DiffRes::Both(lv, rv) => diff_into(document, &path, lv, rv, out),
+                    res => out.push(Change {
+                        document,
+                        path,
+                        res,
+                    }),
+                }
+            }
+        }
+        _ if l != r => out.push(Change {
+            document,
+            path: path.to_owned(),
+            res: DiffRes::Both(l, r),
+        }),
+        _ => (),
+    }
+}
+
+impl DiffAlgo<Stream> for YamlDiff {
+    type Diff<'a> = Vec<Change<'a>>;
+
+    fn diff<'a>(l: &'a Stream, r: &'a Stream) -> Self::Diff<'a> {
+        let mut out = Vec::new();
+        for (document, res) in diff_paired(&l.0, &r.0) {
+            match res {
+                DiffRes::Both(lv, rv) => diff_into(document, "", lv, rv, &mut out),
+                res => out.push(Change {
+                    document,
+                    path: String::new(),
+                    res,
+                }),
+            }
+        }
+        out
+    }
+}
+
+impl DiffAlgo<Stream> for algo::Default {
+    type Diff<'a> = Vec<Change<'a>>;
+
+    fn diff<'a>(l: &'a Stream, r: &'a Stream) -> Self::Diff<'a> {
+        YamlDiff::diff(l, r)
+    }
+}
+
+impl Diffable for Stream {
+    type Diff<'a, A: DiffAlgo<Self::Item>> = A::Diff<'a>;
+    type Item = Stream;
+
+    fn diff<'a, A: DiffAlgo<Self::Item>>(&'a self, other: &'a Self) -> A::Diff<'a> {
+        A::diff(self, other)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_yaml() {
+        let a = parse(
+            "\
+kind: Deployment
+metadata:
+  name: web
+  labels: &labels
+    app: web
+spec:
+  replicas: 2
+  selector: *labels
+  ports: [80, 443]
+---
+kind: Service
+metadata:
+  name: web
+",
+        )
+        .unwrap();
+        let b = parse(
+            "\
+metadata:
+  labels:
+    app: web
+  name: web
+kind: Deployment
+spec:
+  selector:
+    app: web
+  replicas: 3
+  ports: [80, 8443]
+  paused: false
+---
+kind: ConfigMap
+",
+        )
+        .unwrap();
+        let d = crate::diff(&a, &b);
+
+        let lines = d.iter().map(Change::to_string).collect::<Vec<_>>();
+        assert_eq!(
+            lines,
+            vec![
+                " 0:.spec.replicas (line 7 -> 9): 2 -> 3",
+                " 0:.spec.ports[1] (line 9 -> 10): 443 -> 8443",
+                "+0:.spec.paused (line 11): false",
+                " 1:.kind (line 11 -> 13): \"Service\" -> \"ConfigMap\"",
+                "-1:.metadata (line 13): {\"name\": \"web\"}",
+            ],
+        );
+    }
+
+    #[test]
+    fn test_yaml_fmt() {
+        let a = parse("a: 1\n---\nb: [x]\n").unwrap();
+        let b = parse("a: 2\n---\nb: [x, y]\n").unwrap();
+        let mut out = String::new();
+        crate::fmt::fmt_yaml(crate::diff(&a, &b), &mut out).unwrap();
+
+        assert_eq!(
+            out,
+            "\
+@@ document 0 @@
+ 0:.a (line 1 -> 1): 1 -> 2
+@@ document 1 @@
++1:.b[1] (line 3): \"y\"
+",
+        );
+    }
+
+    #[test]
+    fn test_yaml_error() {
+        let err = parse("a: [1, 2\nb: 3").unwrap_err();
+        assert_eq!(err.line, 2);
+    }
+}